@@ -0,0 +1,73 @@
+// Compares the lock count storage of Occupancy on a large generated map, timing the vehicle update loop.
+// Run with `cargo run --release --example occupancy_benchmark`
+
+extern crate hanger_lane;
+extern crate network;
+extern crate rand;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Instant;
+use hanger_lane::{Traffic, Vehicle};
+use hanger_lane::city::City;
+use hanger_lane::lock_counts::{Bitset, Counters, LockCounts};
+use hanger_lane::occupancy::{Holder, Occupancy};
+use hanger_lane::routes::Routes;
+use hanger_lane::simulation::{SimulationState, SimulationStep, UpdateVehicles, VehicleUpdate, create_rng};
+use hanger_lane::steps::block_occupier::{VehicleFree, VehicleOccupy};
+use hanger_lane::steps::lookahead_driver::LookaheadDriver;
+use network::Network;
+use rand::Rng;
+
+const WIDTH: usize = 400;
+const HEIGHT: usize = 400;
+const VEHICLES: usize = 20000;
+const STEPS: usize = 50;
+
+fn run(name: &str, lock_counts: Box<LockCounts>, city: &City, routes: &Rc<RefCell<Routes>>) {
+    let nodes = city.get_num_nodes();
+    let mut occupancy = Occupancy::with_lock_counts(nodes, lock_counts, false);
+
+    // The same vehicles every run, at most one per cell and none facing east, so none start at their destination
+    let mut rng = create_rng(1);
+    let mut vehicles = vec![];
+    while vehicles.len() < VEHICLES {
+        let location = rng.gen_range(0, nodes);
+        if occupancy.is_unlocked(location) && location % 4 != 1 {
+            for node in 4 * (location / 4)..4 * (location / 4) + 4 {
+                occupancy.lock_as(node, Holder::Vehicle(vehicles.len()));
+            }
+            vehicles.push(Vehicle::new(vehicles.len(), location, routes.borrow().targets[0].clone(), 0));
+        }
+    }
+
+    let updates: Vec<Box<VehicleUpdate>> = vec![
+        Box::new(VehicleFree::new(4)),
+        Box::new(LookaheadDriver::new(3, Rc::clone(routes))),
+        Box::new(VehicleOccupy::new(4)),
+    ];
+    let update_vehicles = UpdateVehicles{ updates };
+    let mut state = SimulationState::new(Traffic::new(vehicles), occupancy, Box::new(rand::thread_rng()));
+
+    let start = Instant::now();
+    for _ in 0..STEPS {
+        state = update_vehicles.step(state);
+        state.traffic.vehicles.retain(|v| !v.destination.contains(&v.location));
+    }
+    println!("{}: {:.2}ms per step, {}KB of lock counts", name, start.elapsed().as_secs_f64() * 1000.0 / STEPS as f64,
+        state.occupancy.memory_usage() / 1024);
+}
+
+fn main() {
+    let mut city = City::_with_all_roads(WIDTH, HEIGHT);
+    // Every cell on the right hand edge is a destination
+    city.destinations = vec![(0..HEIGHT).map(|y| city.get_index(&hanger_lane::Cell::new(WIDTH - 1, y, hanger_lane::Direction::East))).collect()];
+    let network = Network::new(city.get_num_nodes(), &city.create_edges());
+    let costs = vec![network.dijkstra(city.destinations[0].clone())];
+    let routes = Rc::new(RefCell::new(Routes::new(network, city.destinations.clone(), costs)));
+    println!("{}x{} map, {} vehicles", WIDTH, HEIGHT, VEHICLES);
+
+    let nodes = city.get_num_nodes();
+    run("counters", Box::new(Counters::new(nodes)), &city, &routes);
+    run("bitset", Box::new(Bitset::new(nodes)), &city, &routes);
+}
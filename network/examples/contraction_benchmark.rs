@@ -0,0 +1,59 @@
+// Compares point to point and nearest target queries on a contraction hierarchy against dijkstra, and times
+// rebuilding the hierarchy after some of the costs change, as congestion re-weighting would.
+// Run with `cargo run --release -p network --example contraction_benchmark`
+
+extern crate network;
+extern crate rand;
+
+use std::time::Instant;
+use network::{Edge, Network};
+use rand::{Rng, SeedableRng, StdRng};
+
+const WIDTH: usize = 100;
+const HEIGHT: usize = 100;
+const QUERIES: usize = 1000;
+const GROUP: usize = 8;
+
+fn main() {
+    let nodes = WIDTH * HEIGHT;
+    let edges = Edge::create_grid(WIDTH, HEIGHT, 1u32, Edge::<u32>::create_4_neighbour_deltas());
+    let network = Network::new(nodes, &edges);
+
+    let mut rng = StdRng::from_seed(&[1]);
+    let queries: Vec<(usize, usize)> = (0..QUERIES).map(|_| (rng.gen_range(0, nodes), rng.gen_range(0, nodes))).collect();
+
+    let start = Instant::now();
+    let hierarchy = network.contraction_hierarchy();
+    println!("{}x{} grid, preprocessing took {:.0}ms", WIDTH, HEIGHT, start.elapsed().as_secs_f64() * 1000.0);
+
+    let reweighted: Vec<Edge<u32>> = edges.iter()
+        .map(|e| Edge::new(e.from, e.to, if rng.gen_weighted_bool(20) { rng.gen_range(1, 11) } else { e.cost }))
+        .collect();
+    let reweighted = Network::new(nodes, &reweighted);
+    let start = Instant::now();
+    reweighted.contraction_hierarchy();
+    println!("rebuilding after 5% of the costs changed took {:.0}ms", start.elapsed().as_secs_f64() * 1000.0);
+
+    let start = Instant::now();
+    let costs: Vec<Option<u32>> = queries.iter().map(|&(from, to)| hierarchy.cost(from, to)).collect();
+    println!("contraction hierarchy: {:.1}us per query", start.elapsed().as_secs_f64() * 1e6 / QUERIES as f64);
+
+    let start = Instant::now();
+    for (&(from, to), cost) in queries.iter().zip(costs.iter()).take(QUERIES / 10) {
+        assert!(network.dijkstra(vec![to])[from] == *cost);
+    }
+    println!("dijkstra (whole cost field): {:.1}us per query", start.elapsed().as_secs_f64() * 1e6 / (QUERIES / 10) as f64);
+
+    let targets: Vec<usize> = (0..GROUP).map(|_| rng.gen_range(0, nodes)).collect();
+    let start = Instant::now();
+    let target_costs = hierarchy.target_costs(&targets);
+    let costs: Vec<Option<u32>> = queries.iter().map(|&(from, _)| hierarchy.cost_to(from, &target_costs)).collect();
+    println!("contraction hierarchy, nearest of {} targets: {:.1}us per query", GROUP, start.elapsed().as_secs_f64() * 1e6 / QUERIES as f64);
+
+    let start = Instant::now();
+    let field = network.dijkstra(targets);
+    println!("dijkstra (whole cost field to {} targets): {:.0}us", GROUP, start.elapsed().as_secs_f64() * 1e6);
+    for (&(from, _), cost) in queries.iter().zip(costs.iter()) {
+        assert!(field[from] == *cost);
+    }
+}
@@ -0,0 +1,84 @@
+// Compares the compressed sparse row Network against the nested vector layout it replaced.
+// Run with `cargo run --release -p network --example grid_benchmark`
+
+extern crate network;
+
+use std::collections::BinaryHeap;
+use std::cmp::Reverse;
+use std::mem::size_of;
+use std::time::Instant;
+use network::{Edge, Network};
+
+const WIDTH: usize = 500;
+const HEIGHT: usize = 500;
+const RUNS: usize = 10;
+
+struct NestedNetwork {
+    edges_out: Vec<Vec<Edge<u32>>>,
+    edges_in: Vec<Vec<Edge<u32>>>,
+}
+
+impl NestedNetwork {
+
+    fn new(nodes: usize, edges: &Vec<Edge<u32>>) -> NestedNetwork {
+        let mut edges_out = vec![vec![]; nodes];
+        let mut edges_in = vec![vec![]; nodes];
+        for edge in edges {
+            edges_out[edge.from].push(edge.clone());
+            edges_in[edge.to].push(edge.clone());
+        }
+        NestedNetwork{ edges_out, edges_in }
+    }
+
+    fn memory_usage(&self) -> usize {
+        let outer = (self.edges_out.capacity() + self.edges_in.capacity()) * size_of::<Vec<Edge<u32>>>();
+        let inner: usize = self.edges_out.iter().chain(self.edges_in.iter())
+            .map(|e| e.capacity() * size_of::<Edge<u32>>())
+            .sum();
+        outer + inner
+    }
+
+    fn dijkstra(&self, nodes: Vec<usize>) -> Vec<Option<u32>> {
+        let mut closed = vec![false; self.edges_in.len()];
+        let mut out = vec![None; self.edges_in.len()];
+        let mut heap = BinaryHeap::new();
+        for node in nodes {
+            heap.push(Reverse((0, node)));
+        }
+        while let Some(Reverse((cost, index))) = heap.pop() {
+            if !closed[index] {
+                closed[index] = true;
+                out[index] = Some(cost);
+                for edge in self.edges_in[index].iter() {
+                    if !closed[edge.from] {
+                        heap.push(Reverse((cost + edge.cost, edge.from)));
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+fn time<F: FnMut()>(mut f: F) -> f64 {
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        f();
+    }
+    start.elapsed().as_secs_f64() * 1000.0 / RUNS as f64
+}
+
+fn main() {
+    let nodes = WIDTH * HEIGHT;
+    let edges = Edge::create_grid(WIDTH, HEIGHT, 1u32, Edge::<u32>::create_4_neighbour_deltas());
+    let targets = vec![0, nodes / 2, nodes - 1];
+
+    let csr = Network::new(nodes, &edges);
+    let nested = NestedNetwork::new(nodes, &edges);
+    assert!(csr.dijkstra(targets.clone()) == nested.dijkstra(targets.clone()));
+
+    println!("{}x{} grid, {} nodes, {} edges", WIDTH, HEIGHT, nodes, edges.len());
+    println!("{:<8} {:>12} {:>16}", "layout", "memory (KB)", "dijkstra (ms)");
+    println!("{:<8} {:>12} {:>16.2}", "nested", nested.memory_usage() / 1024, time(|| { nested.dijkstra(targets.clone()); }));
+    println!("{:<8} {:>12} {:>16.2}", "csr", csr.memory_usage() / 1024, time(|| { csr.dijkstra(targets.clone()); }));
+}
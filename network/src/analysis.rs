@@ -0,0 +1,166 @@
+use std::collections::VecDeque;
+use {Cost, Edge, Network};
+
+impl <C: Cost> Network<C> {
+
+    // Tarjan's algorithm, iterative so that large networks do not overflow the stack
+    pub fn strongly_connected_components(&self) -> Vec<Vec<usize>> {
+        let mut index: Vec<Option<usize>> = vec![None; self.nodes];
+        let mut lowlink = vec![0; self.nodes];
+        let mut on_stack = vec![false; self.nodes];
+        let mut stack = vec![];
+        let mut next_index = 0;
+        let mut components = vec![];
+
+        for root in 0..self.nodes {
+            if index[root].is_some() {
+                continue;
+            }
+            index[root] = Some(next_index);
+            lowlink[root] = next_index;
+            next_index += 1;
+            stack.push(root);
+            on_stack[root] = true;
+            let mut call_stack = vec![(root, 0)];
+
+            while let Some((node, position)) = call_stack.pop() {
+                let out = self.get_out(node);
+                if position < out.len() {
+                    call_stack.push((node, position + 1));
+                    let to = out[position].to;
+                    match index[to] {
+                        None => {
+                            index[to] = Some(next_index);
+                            lowlink[to] = next_index;
+                            next_index += 1;
+                            stack.push(to);
+                            on_stack[to] = true;
+                            call_stack.push((to, 0));
+                        },
+                        Some(to_index) => if on_stack[to] {
+                            lowlink[node] = lowlink[node].min(to_index);
+                        },
+                    }
+                } else {
+                    if let Some(&(parent, _)) = call_stack.last() {
+                        lowlink[parent] = lowlink[parent].min(lowlink[node]);
+                    }
+                    if Some(lowlink[node]) == index[node] {
+                        let mut component = vec![];
+                        while let Some(member) = stack.pop() {
+                            on_stack[member] = false;
+                            component.push(member);
+                            if member == node {
+                                break;
+                            }
+                        }
+                        components.push(component);
+                    }
+                }
+            }
+        }
+
+        components
+    }
+
+    pub fn reachable_from(&self, nodes: &[usize]) -> Vec<bool> {
+        self.search(nodes, |n| self.get_out(n), |e| e.to)
+    }
+
+    pub fn can_reach(&self, nodes: &[usize]) -> Vec<bool> {
+        self.search(nodes, |n| self.get_in(n), |e| e.from)
+    }
+
+    // Nodes that can be reached from the sources but which have no route to any of the destinations
+    pub fn traps(&self, sources: &[usize], destinations: &[usize]) -> Vec<usize> {
+        let reachable = self.reachable_from(sources);
+        let can_reach = self.can_reach(destinations);
+        (0..self.nodes).filter(|n| reachable[*n] && !can_reach[*n]).collect()
+    }
+
+    fn search<'a, F, G>(&'a self, nodes: &[usize], edges: F, next: G) -> Vec<bool>
+        where F: Fn(usize) -> &'a [Edge<C>], G: Fn(&Edge<C>) -> usize {
+        let mut visited = vec![false; self.nodes];
+        let mut queue: VecDeque<usize> = VecDeque::new();
+        for node in nodes {
+            if !visited[*node] {
+                visited[*node] = true;
+                queue.push_back(*node);
+            }
+        }
+        while let Some(node) = queue.pop_front() {
+            for edge in edges(node) {
+                let neighbour = next(edge);
+                if !visited[neighbour] {
+                    visited[neighbour] = true;
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+        visited
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use hamcrest::prelude::*;
+    use {Edge, Network};
+
+    fn get_test_network() -> Network<u32> {
+        let edges = vec![
+            Edge::new(0, 1, 1),
+            Edge::new(1, 2, 1),
+            Edge::new(2, 0, 1),
+            Edge::new(2, 3, 1),
+            Edge::new(3, 4, 1),
+            Edge::new(4, 3, 1),
+            Edge::new(1, 5, 1),
+            Edge::new(6, 0, 1)];
+        Network::new(8, &edges)
+    }
+
+    #[test]
+    fn test_strongly_connected_components() {
+        let mut components: Vec<Vec<usize>> = get_test_network().strongly_connected_components().into_iter()
+            .map(|mut c| { c.sort(); c })
+            .collect();
+        components.sort();
+        let expected = vec![vec![0, 1, 2], vec![3, 4], vec![5], vec![6], vec![7]];
+        assert_that!(&components, is(equal_to(&expected)));
+    }
+
+    #[test]
+    fn test_strongly_connected_components_on_long_chain() {
+        let edges: Vec<Edge<u32>> = (0..100000).map(|n| Edge::new(n, (n + 1) % 100000, 1)).collect();
+        let network = Network::new(100000, &edges);
+        assert_that!(network.strongly_connected_components().len(), is(equal_to(1)));
+    }
+
+    #[test]
+    fn test_reachable_from() {
+        let reachable = get_test_network().reachable_from(&[3]);
+        let expected = vec![false, false, false, true, true, false, false, false];
+        assert_that!(&reachable, is(equal_to(&expected)));
+        let reachable = get_test_network().reachable_from(&[6, 7]);
+        let expected = vec![true, true, true, true, true, true, true, true];
+        assert_that!(&reachable, is(equal_to(&expected)));
+    }
+
+    #[test]
+    fn test_can_reach() {
+        let can_reach = get_test_network().can_reach(&[5]);
+        let expected = vec![true, true, true, false, false, true, true, false];
+        assert_that!(&can_reach, is(equal_to(&expected)));
+    }
+
+    #[test]
+    fn test_traps() {
+        let expected = vec![3, 4];
+        assert_that!(&get_test_network().traps(&[6], &[5]), is(equal_to(&expected)));
+        let expected = vec![5];
+        assert_that!(&get_test_network().traps(&[6], &[3]), is(equal_to(&expected)));
+    }
+
+}
@@ -0,0 +1,153 @@
+use {Cost, Network};
+
+pub struct Demand {
+    pub sources: Vec<usize>,
+    pub destinations: Vec<usize>,
+    pub weight: f64,
+}
+
+impl Demand {
+
+    pub fn new(sources: Vec<usize>, destinations: Vec<usize>, weight: f64) -> Demand {
+        Demand{ sources, destinations, weight }
+    }
+
+}
+
+// Node and edge values are the expected flow through each node and edge, with edges in the order of Network::edges
+#[derive(Debug, PartialEq)]
+pub struct Betweenness {
+    pub nodes: Vec<f64>,
+    pub edges: Vec<f64>,
+}
+
+impl <C: Cost> Network<C> {
+
+    // Each demand's weight is shared equally between its sources that have a route, then split over
+    // shortest paths to the destinations in proportion to the number of shortest paths through each edge.
+    // Edge costs are assumed to be positive.
+    pub fn betweenness(&self, demands: &[Demand]) -> Betweenness {
+        let mut out = Betweenness{ nodes: vec![0.0; self.nodes], edges: vec![0.0; self.out_edges.len()] };
+
+        for demand in demands {
+            let costs = self.dijkstra(demand.destinations.clone());
+            let mut is_destination = vec![false; self.nodes];
+            for destination in demand.destinations.iter() {
+                is_destination[*destination] = true;
+            }
+
+            let mut order: Vec<usize> = (0..self.nodes).filter(|n| costs[*n].is_some()).collect();
+            order.sort_by_key(|n| costs[*n]);
+
+            let is_tight = |from: usize, to: usize, cost: C| costs[to].map(|c| c + cost) == costs[from];
+
+            let mut paths = vec![0.0; self.nodes];
+            for node in order.iter().cloned() {
+                paths[node] = if is_destination[node] {
+                    1.0
+                } else {
+                    self.get_out(node).iter()
+                        .filter(|e| is_tight(node, e.to, e.cost))
+                        .map(|e| paths[e.to])
+                        .sum()
+                };
+            }
+
+            let sources: Vec<usize> = demand.sources.iter().cloned().filter(|s| costs[*s].is_some()).collect();
+            let mut flow = vec![0.0; self.nodes];
+            for source in sources.iter() {
+                flow[*source] += demand.weight / sources.len() as f64;
+            }
+
+            for node in order.iter().rev().cloned() {
+                if flow[node] == 0.0 {
+                    continue;
+                }
+                out.nodes[node] += flow[node];
+                if is_destination[node] {
+                    continue;
+                }
+                let first_edge = self.out_offsets[node];
+                for (offset, edge) in self.get_out(node).iter().enumerate() {
+                    if is_tight(node, edge.to, edge.cost) {
+                        let share = flow[node] * paths[edge.to] / paths[node];
+                        out.edges[first_edge + offset] += share;
+                        flow[edge.to] += share;
+                    }
+                }
+            }
+        }
+
+        out
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use hamcrest::prelude::*;
+    use betweenness::Demand;
+    use {Edge, Network};
+
+    // 0 -> 1 -> 3 and 0 -> 2 -> 3 are both shortest paths, 0 -> 4 -> 3 is longer
+    fn get_test_network() -> Network<u32> {
+        let edges = vec![
+            Edge::new(0, 1, 1),
+            Edge::new(0, 2, 1),
+            Edge::new(0, 4, 2),
+            Edge::new(1, 3, 1),
+            Edge::new(2, 3, 1),
+            Edge::new(4, 3, 1),
+            Edge::new(5, 3, 1)];
+        Network::new(6, &edges)
+    }
+
+    #[test]
+    fn test_flow_split_over_shortest_paths() {
+        let network = get_test_network();
+        let betweenness = network.betweenness(&[Demand::new(vec![0], vec![3], 4.0)]);
+        let expected = vec![4.0, 2.0, 2.0, 4.0, 0.0, 0.0];
+        assert_that!(&betweenness.nodes, is(equal_to(&expected)));
+        let expected = vec![2.0, 2.0, 0.0, 2.0, 2.0, 0.0, 0.0];
+        assert_that!(&betweenness.edges, is(equal_to(&expected)));
+    }
+
+    #[test]
+    fn test_flow_split_over_sources() {
+        let network = get_test_network();
+        let betweenness = network.betweenness(&[Demand::new(vec![0, 5], vec![3], 2.0)]);
+        let expected = vec![1.0, 0.5, 0.5, 2.0, 0.0, 1.0];
+        assert_that!(&betweenness.nodes, is(equal_to(&expected)));
+    }
+
+    #[test]
+    fn test_sources_without_route_ignored() {
+        let network = get_test_network();
+        let betweenness = network.betweenness(&[Demand::new(vec![3, 4], vec![1], 1.0)]);
+        let expected = vec![0.0; 6];
+        assert_that!(&betweenness.nodes, is(equal_to(&expected)));
+    }
+
+    #[test]
+    fn test_demands_are_summed() {
+        let network = get_test_network();
+        let betweenness = network.betweenness(&[
+            Demand::new(vec![0], vec![3], 1.0),
+            Demand::new(vec![4], vec![3], 3.0)]);
+        let expected = vec![1.0, 0.5, 0.5, 4.0, 3.0, 0.0];
+        assert_that!(&betweenness.nodes, is(equal_to(&expected)));
+    }
+
+    #[test]
+    fn test_path_counting_on_grid() {
+        let edges = Edge::create_grid(3, 3, 1, Edge::<u32>::create_4_neighbour_deltas());
+        let network = Network::new(9, &edges);
+        let betweenness = network.betweenness(&[Demand::new(vec![0], vec![8], 6.0)]);
+        // Of the six shortest paths from corner to corner, three pass through 1, four through the centre and one through 2
+        assert_that!(betweenness.nodes[1], is(equal_to(3.0)));
+        assert_that!(betweenness.nodes[4], is(equal_to(4.0)));
+        assert_that!(betweenness.nodes[2], is(equal_to(1.0)));
+    }
+
+}
@@ -0,0 +1,236 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use {Cost, Network, OrderedFloat};
+
+const MAGIC: &[u8; 4] = b"HLCF";
+const VERSION: u32 = 1;
+
+pub trait CachedCost: Cost {
+    const SIZE: usize;
+    fn write_bytes(&self, out: &mut Vec<u8>);
+    fn read_bytes(bytes: &[u8]) -> Self;
+}
+
+macro_rules! cached_cost {
+    ($t:ty, $size:expr, $wrap:expr, $unwrap:expr) => {
+        impl CachedCost for $t {
+            const SIZE: usize = $size;
+
+            fn write_bytes(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&$unwrap(*self).to_le_bytes());
+            }
+
+            fn read_bytes(bytes: &[u8]) -> $t {
+                let mut array = [0; $size];
+                array.copy_from_slice(&bytes[..$size]);
+                $wrap(array)
+            }
+        }
+    }
+}
+
+cached_cost!(u8, 1, u8::from_le_bytes, |c: u8| c);
+cached_cost!(u16, 2, u16::from_le_bytes, |c: u16| c);
+cached_cost!(u32, 4, u32::from_le_bytes, |c: u32| c);
+cached_cost!(u64, 8, u64::from_le_bytes, |c: u64| c);
+cached_cost!(usize, 8, |a| u64::from_le_bytes(a) as usize, |c: usize| c as u64);
+cached_cost!(i32, 4, i32::from_le_bytes, |c: i32| c);
+cached_cost!(i64, 8, i64::from_le_bytes, |c: i64| c);
+cached_cost!(OrderedFloat<f32>, 4, |a| OrderedFloat(f32::from_le_bytes(a)), |c: OrderedFloat<f32>| c.0);
+cached_cost!(OrderedFloat<f64>, 8, |a| OrderedFloat(f64::from_le_bytes(a)), |c: OrderedFloat<f64>| c.0);
+
+// FNV-1a, used instead of DefaultHasher because the hash has to be stable between builds
+struct Fingerprint(u64);
+
+impl Fingerprint {
+
+    fn new() -> Fingerprint {
+        Fingerprint(0xcbf29ce484222325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        self.write(&(value as u64).to_le_bytes());
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl <C: CachedCost> Network<C> {
+
+    pub fn fingerprint(&self, targets: &[Vec<usize>]) -> u64 {
+        let mut fingerprint = Fingerprint::new();
+        let mut bytes = vec![];
+        fingerprint.write_usize(self.nodes);
+        fingerprint.write_usize(self.edges().len());
+        for edge in self.edges() {
+            fingerprint.write_usize(edge.from);
+            fingerprint.write_usize(edge.to);
+            bytes.clear();
+            edge.cost.write_bytes(&mut bytes);
+            fingerprint.write(&bytes);
+        }
+        fingerprint.write_usize(targets.len());
+        for nodes in targets {
+            fingerprint.write_usize(nodes.len());
+            for node in nodes {
+                fingerprint.write_usize(*node);
+            }
+        }
+        fingerprint.0
+    }
+
+    pub fn save_costs<P: AsRef<Path>>(&self, path: P, targets: &[Vec<usize>], costs: &[Vec<Option<C>>]) -> io::Result<()> {
+        let mut out = vec![];
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&self.fingerprint(targets).to_le_bytes());
+        out.extend_from_slice(&(C::SIZE as u32).to_le_bytes());
+        out.extend_from_slice(&(self.nodes as u64).to_le_bytes());
+        out.extend_from_slice(&(costs.len() as u64).to_le_bytes());
+        for field in costs {
+            for cost in field {
+                match *cost {
+                    Some(ref cost) => {
+                        out.push(1);
+                        cost.write_bytes(&mut out);
+                    },
+                    None => out.push(0),
+                }
+            }
+        }
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(&out)?;
+        writer.flush()
+    }
+
+    // Returns None if the file was written for a different network or different targets
+    pub fn load_costs<P: AsRef<Path>>(&self, path: P, targets: &[Vec<usize>]) -> io::Result<Option<Vec<Vec<Option<C>>>>> {
+        let mut bytes = vec![];
+        BufReader::new(File::open(path)?).read_to_end(&mut bytes)?;
+
+        let header = 4 + 4 + 8 + 4 + 8 + 8;
+        if bytes.len() < header || &bytes[0..4] != MAGIC {
+            return Err(invalid_data("Not a cost cache file"));
+        }
+        let read_u32 = |at: usize| u32::read_bytes(&bytes[at..]);
+        let read_u64 = |at: usize| u64::read_bytes(&bytes[at..]);
+        if read_u32(4) != VERSION {
+            return Ok(None);
+        }
+        if read_u64(8) != self.fingerprint(targets) || read_u32(16) as usize != C::SIZE || read_u64(20) as usize != self.nodes {
+            return Ok(None);
+        }
+        let fields = read_u64(28) as usize;
+
+        let mut position = header;
+        let mut out = Vec::with_capacity(fields);
+        for _ in 0..fields {
+            let mut field = Vec::with_capacity(self.nodes);
+            for _ in 0..self.nodes {
+                match bytes.get(position) {
+                    Some(&0) => {
+                        field.push(None);
+                        position += 1;
+                    },
+                    Some(&1) if position + 1 + C::SIZE <= bytes.len() => {
+                        field.push(Some(C::read_bytes(&bytes[position + 1..])));
+                        position += 1 + C::SIZE;
+                    },
+                    _ => return Err(invalid_data("Cost cache file is truncated or corrupt")),
+                }
+            }
+            out.push(field);
+        }
+        Ok(Some(out))
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+    use hamcrest::prelude::*;
+    use {Edge, Network, OrderedFloat};
+
+    fn get_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("network_cache_{}_{}", name, std::process::id()))
+    }
+
+    fn get_targets() -> Vec<Vec<usize>> {
+        vec![vec![0], vec![5, 6], vec![15]]
+    }
+
+    #[test]
+    fn test_save_then_load() {
+        let path = get_path("save_then_load");
+        let edges = Edge::create_grid(4, 4, 1u32, Edge::<u32>::create_4_neighbour_deltas());
+        let network = Network::new(16, &edges);
+        let costs = network.dijkstra_all(get_targets(), 1);
+        network.save_costs(&path, &get_targets(), &costs).unwrap();
+        let loaded = network.load_costs(&path, &get_targets()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_that!(loaded, is(equal_to(Some(costs))));
+    }
+
+    #[test]
+    fn test_save_then_load_fractional() {
+        let path = get_path("save_then_load_fractional");
+        let edges = Edge::create_grid(4, 4, OrderedFloat(0.5), Edge::<OrderedFloat<f64>>::create_4_neighbour_deltas());
+        let network = Network::new(16, &edges);
+        let costs = network.dijkstra_all(get_targets(), 1);
+        network.save_costs(&path, &get_targets(), &costs).unwrap();
+        let loaded = network.load_costs(&path, &get_targets()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_that!(loaded, is(equal_to(Some(costs))));
+    }
+
+    #[test]
+    fn test_load_after_network_changed() {
+        let path = get_path("load_after_network_changed");
+        let edges = Edge::create_grid(4, 4, 1u32, Edge::<u32>::create_4_neighbour_deltas());
+        let mut network = Network::new(16, &edges);
+        let costs = network.dijkstra_all(get_targets(), 1);
+        network.save_costs(&path, &get_targets(), &costs).unwrap();
+        network.set_cost(0, 1, 2);
+        let loaded = network.load_costs(&path, &get_targets()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_that!(loaded, is(equal_to(None)));
+    }
+
+    #[test]
+    fn test_load_after_targets_changed() {
+        let path = get_path("load_after_targets_changed");
+        let edges = Edge::create_grid(4, 4, 1u32, Edge::<u32>::create_4_neighbour_deltas());
+        let network = Network::new(16, &edges);
+        let costs = network.dijkstra_all(get_targets(), 1);
+        network.save_costs(&path, &get_targets(), &costs).unwrap();
+        let loaded = network.load_costs(&path, &[vec![1]]).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_that!(loaded, is(equal_to(None)));
+    }
+
+    #[test]
+    fn test_load_corrupt_file() {
+        let path = get_path("load_corrupt_file");
+        fs::write(&path, b"not a cache").unwrap();
+        let edges = Edge::create_grid(4, 4, 1u32, Edge::<u32>::create_4_neighbour_deltas());
+        let network = Network::new(16, &edges);
+        let loaded = network.load_costs(&path, &get_targets());
+        fs::remove_file(&path).unwrap();
+        assert!(loaded.is_err());
+    }
+
+}
@@ -0,0 +1,368 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use paths::Path;
+use {Cost, Network};
+
+// Witness searches give up after settling this many nodes. Giving up early only adds unnecessary shortcuts.
+const WITNESS_SETTLE_LIMIT: usize = 64;
+
+#[derive(Clone, Debug)]
+struct Shortcut<C> {
+    node: usize,
+    cost: C,
+    middle: Option<usize>,
+}
+
+// Every node is given a rank and queries only ever move from lower to higher ranked nodes. up[n] holds the edges
+// out of n to higher ranked nodes and down[n] the edges into n from higher ranked nodes. Shortcuts remember the node
+// they replace so that paths can be unpacked.
+pub struct ContractionHierarchy<C> {
+    rank: Vec<usize>,
+    up: Vec<Vec<Shortcut<C>>>,
+    down: Vec<Vec<Shortcut<C>>>,
+}
+
+// The cost of reaching the nearest of a group of targets from every node above them in the hierarchy
+pub struct TargetCosts<C> {
+    costs: HashMap<usize, C>,
+}
+
+struct Graph<C> {
+    out: Vec<Vec<Shortcut<C>>>,
+    into: Vec<Vec<Shortcut<C>>>,
+    contracted: Vec<bool>,
+}
+
+impl <C: Cost> Graph<C> {
+
+    fn add(&mut self, from: usize, to: usize, cost: C, middle: Option<usize>) {
+        match self.out[from].iter().position(|s| s.node == to) {
+            Some(i) if self.out[from][i].cost <= cost => {},
+            Some(i) => {
+                self.out[from][i] = Shortcut{ node: to, cost, middle };
+                let j = self.into[to].iter().position(|s| s.node == from).unwrap();
+                self.into[to][j] = Shortcut{ node: from, cost, middle };
+            },
+            None => {
+                self.out[from].push(Shortcut{ node: to, cost, middle });
+                self.into[to].push(Shortcut{ node: from, cost, middle });
+            },
+        }
+    }
+
+    // Takes the edges out of (or into) node, removing them from the nodes at the other end
+    fn remove(&mut self, node: usize, out: bool) -> Vec<Shortcut<C>> {
+        let (edges, others) = if out { (&mut self.out, &mut self.into) } else { (&mut self.into, &mut self.out) };
+        let removed = ::std::mem::take(&mut edges[node]);
+        for edge in removed.iter() {
+            others[edge.node].retain(|s| s.node != node);
+        }
+        removed
+    }
+
+    // Shortcuts needed to keep shortest paths between the remaining nodes if node was removed
+    fn shortcuts(&self, node: usize) -> Vec<(usize, usize, C)> {
+        let mut out = vec![];
+        for from in self.into[node].iter() {
+            let targets: Vec<(usize, C)> = self.out[node].iter()
+                .filter(|s| s.node != from.node)
+                .map(|s| (s.node, from.cost + s.cost))
+                .collect();
+            if targets.is_empty() {
+                continue;
+            }
+            let witnesses = self.witness_search(from.node, node, &targets);
+            for (&(to, cost), witness) in targets.iter().zip(witnesses) {
+                if witness.map_or(true, |w| w > cost) {
+                    out.push((from.node, to, cost));
+                }
+            }
+        }
+        out
+    }
+
+    // The cost of the shortest path found to each target which avoids the given node
+    fn witness_search(&self, from: usize, avoid: usize, targets: &[(usize, C)]) -> Vec<Option<C>> {
+        let limit = targets.iter().map(|t| t.1).max().unwrap();
+        let mut out = vec![None; targets.len()];
+        let mut remaining = targets.len();
+        let mut settled: Vec<usize> = vec![];
+        let mut heap = BinaryHeap::new();
+        heap.push(Reverse((C::zero(), from)));
+        while let Some(Reverse((cost, node))) = heap.pop() {
+            if cost > limit || remaining == 0 || settled.len() >= WITNESS_SETTLE_LIMIT {
+                break;
+            }
+            if settled.contains(&node) {
+                continue;
+            }
+            settled.push(node);
+            for (i, target) in targets.iter().enumerate() {
+                if target.0 == node && out[i].is_none() {
+                    out[i] = Some(cost);
+                    remaining -= 1;
+                }
+            }
+            for edge in self.out[node].iter().filter(|s| s.node != avoid) {
+                heap.push(Reverse((cost + edge.cost, edge.node)));
+            }
+        }
+        out
+    }
+
+    fn priority(&self, node: usize, contracted_neighbours: &[usize]) -> i64 {
+        let degree = self.out[node].len() + self.into[node].len();
+        2 * (self.shortcuts(node).len() as i64 - degree as i64) + contracted_neighbours[node] as i64
+    }
+
+}
+
+impl <C: Cost> Network<C> {
+
+    pub fn contraction_hierarchy(&self) -> ContractionHierarchy<C> {
+        let mut graph = Graph{ out: vec![vec![]; self.nodes], into: vec![vec![]; self.nodes], contracted: vec![false; self.nodes] };
+        for edge in self.edges().iter().filter(|e| e.from != e.to) {
+            graph.add(edge.from, edge.to, edge.cost, None);
+        }
+
+        let mut contracted_neighbours = vec![0; self.nodes];
+        let mut heap: BinaryHeap<Reverse<(i64, usize)>> = (0..self.nodes)
+            .map(|n| Reverse((graph.priority(n, &contracted_neighbours), n)))
+            .collect();
+
+        let mut rank = vec![0; self.nodes];
+        let mut up = vec![vec![]; self.nodes];
+        let mut down = vec![vec![]; self.nodes];
+        let mut next_rank = 0;
+
+        while let Some(Reverse((priority, node))) = heap.pop() {
+            if graph.contracted[node] {
+                continue;
+            }
+            // Priorities go stale as neighbours are contracted, so check again before contracting
+            let current = graph.priority(node, &contracted_neighbours);
+            if current > priority {
+                if let Some(&Reverse((next, _))) = heap.peek() {
+                    if current > next {
+                        heap.push(Reverse((current, node)));
+                        continue;
+                    }
+                }
+            }
+
+            for (from, to, cost) in graph.shortcuts(node) {
+                graph.add(from, to, cost, Some(node));
+            }
+            graph.contracted[node] = true;
+            up[node] = graph.remove(node, true);
+            down[node] = graph.remove(node, false);
+            for neighbour in up[node].iter().chain(down[node].iter()) {
+                contracted_neighbours[neighbour.node] += 1;
+            }
+            rank[node] = next_rank;
+            next_rank += 1;
+        }
+
+        ContractionHierarchy{ rank, up, down }
+    }
+
+}
+
+impl <C: Cost> ContractionHierarchy<C> {
+
+    pub fn cost(&self, from: usize, to: usize) -> Option<C> {
+        self.search(from, to).map(|p| p.cost)
+    }
+
+    // Searches upwards from every target at once, so that the cost from any node to the nearest target only needs a
+    // search upwards from that node
+    pub fn target_costs(&self, targets: &[usize]) -> TargetCosts<C> {
+        let mut costs = HashMap::new();
+        let mut heap: BinaryHeap<Reverse<(C, usize)>> = targets.iter().map(|&t| Reverse((C::zero(), t))).collect();
+        while let Some(Reverse((cost, node))) = heap.pop() {
+            if costs.contains_key(&node) {
+                continue;
+            }
+            costs.insert(node, cost);
+            for edge in self.down[node].iter().filter(|s| !costs.contains_key(&s.node)) {
+                heap.push(Reverse((cost + edge.cost, edge.node)));
+            }
+        }
+        TargetCosts{ costs }
+    }
+
+    pub fn cost_to(&self, from: usize, targets: &TargetCosts<C>) -> Option<C> {
+        let mut settled = HashSet::new();
+        let mut heap = BinaryHeap::new();
+        heap.push(Reverse((C::zero(), from)));
+        let mut best: Option<C> = None;
+        while let Some(Reverse((cost, node))) = heap.pop() {
+            if best.is_some_and(|b| cost >= b) {
+                break;
+            }
+            if !settled.insert(node) {
+                continue;
+            }
+            if let Some(&to_target) = targets.costs.get(&node) {
+                if best.map_or(true, |b| cost + to_target < b) {
+                    best = Some(cost + to_target);
+                }
+            }
+            for edge in self.up[node].iter().filter(|s| !settled.contains(&s.node)) {
+                heap.push(Reverse((cost + edge.cost, edge.node)));
+            }
+        }
+        best
+    }
+
+    pub fn path(&self, from: usize, to: usize) -> Option<Path<C>> {
+        let packed = self.search(from, to)?;
+        let mut nodes = vec![from];
+        for pair in packed.nodes.windows(2) {
+            self.unpack(pair[0], pair[1], &mut nodes);
+        }
+        Some(Path{ nodes, cost: packed.cost })
+    }
+
+    // Bidirectional dijkstra, upwards from both ends. The path returned still contains shortcuts.
+    fn search(&self, from: usize, to: usize) -> Option<Path<C>> {
+        // Search spaces are small, so settled nodes are kept in maps rather than vectors over every node
+        let mut settled: [HashMap<usize, (C, usize)>; 2] = [HashMap::new(), HashMap::new()];
+        let mut heaps = [BinaryHeap::new(), BinaryHeap::new()];
+        heaps[0].push(Reverse((C::zero(), from, from)));
+        heaps[1].push(Reverse((C::zero(), to, to)));
+        let mut best: Option<(C, usize)> = None;
+
+        loop {
+            let direction = match (heaps[0].peek(), heaps[1].peek()) {
+                (None, None) => break,
+                (Some(_), None) => 0,
+                (None, Some(_)) => 1,
+                (Some(&Reverse((a, _, _))), Some(&Reverse((b, _, _)))) => if a <= b { 0 } else { 1 },
+            };
+            let Reverse((cost, node, parent)) = heaps[direction].pop().unwrap();
+            if best.is_some_and(|(b, _)| cost >= b) {
+                heaps[direction].clear();
+                continue;
+            }
+            if settled[direction].contains_key(&node) {
+                continue;
+            }
+            settled[direction].insert(node, (cost, parent));
+            if let Some(&(other, _)) = settled[1 - direction].get(&node) {
+                if best.map_or(true, |(b, _)| cost + other < b) {
+                    best = Some((cost + other, node));
+                }
+            }
+            let edges = if direction == 0 { &self.up[node] } else { &self.down[node] };
+            for edge in edges.iter() {
+                if !settled[direction].contains_key(&edge.node) {
+                    heaps[direction].push(Reverse((cost + edge.cost, edge.node, node)));
+                }
+            }
+        }
+
+        let (cost, meeting) = best?;
+        let mut nodes = vec![meeting];
+        while *nodes.last().unwrap() != from {
+            nodes.push(settled[0][nodes.last().unwrap()].1);
+        }
+        nodes.reverse();
+        let mut node = meeting;
+        while node != to {
+            node = settled[1][&node].1;
+            nodes.push(node);
+        }
+        Some(Path{ nodes, cost })
+    }
+
+    fn find(&self, from: usize, to: usize) -> &Shortcut<C> {
+        let candidates: Vec<&Shortcut<C>> = if self.rank[from] < self.rank[to] {
+            self.up[from].iter().filter(|s| s.node == to).collect()
+        } else {
+            self.down[to].iter().filter(|s| s.node == from).collect()
+        };
+        candidates.into_iter().min_by_key(|s| s.cost).unwrap()
+    }
+
+    fn unpack(&self, from: usize, to: usize, nodes: &mut Vec<usize>) {
+        match self.find(from, to).middle {
+            Some(middle) => {
+                self.unpack(from, middle, nodes);
+                self.unpack(middle, to, nodes);
+            },
+            None => nodes.push(to),
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use hamcrest::prelude::*;
+    use rand::{Rng, SeedableRng, StdRng};
+    use {Edge, Network};
+
+    fn assert_matches_dijkstra(network: &Network<u32>) {
+        let hierarchy = network.contraction_hierarchy();
+        for to in 0..network.nodes {
+            for (from, expected) in network.dijkstra(vec![to]).into_iter().enumerate() {
+                assert_that!(hierarchy.cost(from, to), is(equal_to(expected)));
+                if let Some(path) = hierarchy.path(from, to) {
+                    assert_that!(path.nodes[0], is(equal_to(from)));
+                    assert_that!(*path.nodes.last().unwrap(), is(equal_to(to)));
+                    let cost: u32 = path.nodes.windows(2)
+                        .map(|w| network.get_out(w[0]).iter().filter(|e| e.to == w[1]).map(|e| e.cost).min().unwrap())
+                        .sum();
+                    assert_that!(Some(cost), is(equal_to(expected)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_grid() {
+        let edges = Edge::create_grid(6, 5, 1, Edge::<u32>::create_4_neighbour_deltas());
+        assert_matches_dijkstra(&Network::new(30, &edges));
+    }
+
+    #[test]
+    fn test_cost_to_nearest_target() {
+        let edges = Edge::create_grid(6, 5, 1, Edge::<u32>::create_4_neighbour_deltas());
+        let network = Network::new(30, &edges);
+        let hierarchy = network.contraction_hierarchy();
+        for targets in [vec![0], vec![7, 29], vec![3, 14, 25]].iter() {
+            let target_costs = hierarchy.target_costs(targets);
+            for (from, expected) in network.dijkstra(targets.clone()).into_iter().enumerate() {
+                assert_that!(hierarchy.cost_to(from, &target_costs), is(equal_to(expected)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_path_on_line() {
+        let edges = vec![Edge::new(0, 1, 1), Edge::new(1, 2, 2), Edge::new(2, 3, 3), Edge::new(0, 3, 7)];
+        let network = Network::new(5, &edges);
+        let hierarchy = network.contraction_hierarchy();
+        let expected = vec![0, 1, 2, 3];
+        assert_that!(&hierarchy.path(0, 3).unwrap().nodes, is(equal_to(&expected)));
+        assert_that!(hierarchy.cost(0, 3), is(equal_to(Some(6))));
+        assert_that!(hierarchy.cost(3, 0), is(equal_to(None)));
+        assert_that!(hierarchy.cost(0, 4), is(equal_to(None)));
+        assert_that!(hierarchy.cost(2, 2), is(equal_to(Some(0))));
+    }
+
+    #[test]
+    fn test_random_graphs() {
+        let mut rng = StdRng::from_seed(&[98765]);
+        for _ in 0..20 {
+            let nodes = rng.gen_range(10, 40);
+            let edges: Vec<Edge<u32>> = (0..nodes * 3)
+                .map(|_| Edge::new(rng.gen_range(0, nodes), rng.gen_range(0, nodes), rng.gen_range(1, 21)))
+                .collect();
+            assert_matches_dijkstra(&Network::new(nodes, &edges));
+        }
+    }
+
+}
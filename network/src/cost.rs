@@ -0,0 +1,91 @@
+use std::cmp::Ordering;
+use std::fmt::{self, Debug, Display};
+use std::ops::Add;
+use num::Zero;
+
+pub trait Cost: Copy + Ord + Add<Output = Self> + Zero + Debug {}
+
+impl <T> Cost for T where T: Copy + Ord + Add<Output = T> + Zero + Debug {}
+
+// Floats are only partially ordered, so they need wrapping before they can be used as costs
+#[derive(Clone, Copy, Debug)]
+pub struct OrderedFloat<T>(pub T);
+
+macro_rules! ordered_float {
+    ($t:ty) => {
+        impl PartialEq for OrderedFloat<$t> {
+            fn eq(&self, other: &OrderedFloat<$t>) -> bool {
+                self.cmp(other) == Ordering::Equal
+            }
+        }
+
+        impl Eq for OrderedFloat<$t> {}
+
+        impl PartialOrd for OrderedFloat<$t> {
+            fn partial_cmp(&self, other: &OrderedFloat<$t>) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for OrderedFloat<$t> {
+            fn cmp(&self, other: &OrderedFloat<$t>) -> Ordering {
+                self.0.total_cmp(&other.0)
+            }
+        }
+
+        impl Add for OrderedFloat<$t> {
+            type Output = OrderedFloat<$t>;
+
+            fn add(self, other: OrderedFloat<$t>) -> OrderedFloat<$t> {
+                OrderedFloat(self.0 + other.0)
+            }
+        }
+
+        impl Display for OrderedFloat<$t> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                Display::fmt(&self.0, f)
+            }
+        }
+
+        impl Zero for OrderedFloat<$t> {
+            fn zero() -> OrderedFloat<$t> {
+                OrderedFloat(0.0)
+            }
+
+            // Agrees with Ord, so -0.0 is not zero
+            fn is_zero(&self) -> bool {
+                self.cmp(&OrderedFloat::zero()) == Ordering::Equal
+            }
+        }
+    }
+}
+
+ordered_float!(f32);
+ordered_float!(f64);
+
+#[cfg(test)]
+mod tests {
+
+    use num::Zero;
+    use cost::OrderedFloat;
+
+    #[test]
+    fn test_ordered_float_ordering() {
+        let mut costs = vec![OrderedFloat(2.5), OrderedFloat(0.25), OrderedFloat(1.0)];
+        costs.sort();
+        assert!(costs == vec![OrderedFloat(0.25), OrderedFloat(1.0), OrderedFloat(2.5)]);
+    }
+
+    #[test]
+    fn test_ordered_float_is_zero_matches_ordering() {
+        assert!(OrderedFloat(0.0f64).is_zero());
+        assert!(!OrderedFloat(-0.0f64).is_zero());
+        assert!(OrderedFloat(-0.0f64) < OrderedFloat::zero());
+    }
+
+    #[test]
+    fn test_ordered_float_add() {
+        assert!(OrderedFloat(0.5f32) + OrderedFloat(0.25f32) == OrderedFloat(0.75f32));
+    }
+
+}
@@ -0,0 +1,177 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use {Cost, Network};
+
+impl <C: Cost> Network<C> {
+
+    // Repairs costs previously returned by dijkstra(targets) after the edges leaving the changed nodes have been
+    // added, removed or re-weighted. Only nodes whose cost depended on a changed edge are recalculated.
+    // Edge costs are assumed to be positive.
+    pub fn update_dijkstra(&self, costs: &mut [Option<C>], targets: &[usize], changed: &[usize]) {
+        let mut is_target = vec![false; self.nodes];
+        for target in targets {
+            is_target[*target] = true;
+        }
+
+        let mut invalid = vec![false; self.nodes];
+        let mut stack: Vec<usize> = changed.iter()
+            .cloned()
+            .filter(|n| !is_target[*n] && costs[*n].is_some() && !self.is_supported(*n, costs))
+            .collect();
+
+        while let Some(node) = stack.pop() {
+            if invalid[node] {
+                continue;
+            }
+            invalid[node] = true;
+            let cost = costs[node].unwrap();
+            for edge in self.get_in(node) {
+                if !is_target[edge.from] && !invalid[edge.from] && costs[edge.from] == Some(cost + edge.cost) {
+                    stack.push(edge.from);
+                }
+            }
+        }
+
+        let mut heap = BinaryHeap::new();
+
+        for node in 0..self.nodes {
+            if invalid[node] {
+                costs[node] = None;
+            }
+        }
+
+        for node in (0..self.nodes).filter(|n| invalid[*n]).chain(changed.iter().cloned()) {
+            if let Some(cost) = self.best_via_out(node, costs) {
+                if costs[node].map_or(true, |current| cost < current) {
+                    heap.push(Reverse((cost, node)));
+                }
+            }
+        }
+
+        while let Some(Reverse((cost, node))) = heap.pop() {
+            if costs[node].is_some_and(|current| current <= cost) {
+                continue;
+            }
+            costs[node] = Some(cost);
+            for edge in self.get_in(node) {
+                let candidate = cost + edge.cost;
+                if costs[edge.from].map_or(true, |current| candidate < current) {
+                    heap.push(Reverse((candidate, edge.from)));
+                }
+            }
+        }
+    }
+
+    fn is_supported(&self, node: usize, costs: &[Option<C>]) -> bool {
+        self.get_out(node).iter()
+            .any(|e| costs[e.to].map(|c| c + e.cost) == costs[node])
+    }
+
+    fn best_via_out(&self, node: usize, costs: &[Option<C>]) -> Option<C> {
+        self.get_out(node).iter()
+            .filter_map(|e| costs[e.to].map(|c| c + e.cost))
+            .min()
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use hamcrest::prelude::*;
+    use rand::{Rng, SeedableRng, StdRng};
+    use {Edge, Network};
+
+    fn get_grid() -> Network<u32> {
+        let edges = Edge::create_grid(4, 4, 1, Edge::<u32>::create_4_neighbour_deltas());
+        Network::new(16, &edges)
+    }
+
+    fn assert_update_matches_dijkstra(network: &Network<u32>, costs: &mut Vec<Option<u32>>, targets: Vec<usize>, changed: &[usize]) {
+        network.update_dijkstra(costs, &targets, changed);
+        let expected = network.dijkstra(targets);
+        assert_that!(&*costs, is(equal_to(&expected[..])));
+    }
+
+    #[test]
+    fn test_update_after_cost_decrease() {
+        let mut network = get_grid();
+        let mut costs = network.dijkstra(vec![0]);
+        network.add_edge(Edge::new(15, 0, 1));
+        assert_update_matches_dijkstra(&network, &mut costs, vec![0], &[15]);
+        assert_that!(costs[15], is(equal_to(Some(1))));
+        assert_that!(costs[14], is(equal_to(Some(2))));
+    }
+
+    #[test]
+    fn test_update_after_cost_increase() {
+        let mut network = get_grid();
+        let mut costs = network.dijkstra(vec![0]);
+        network.set_cost(1, 0, 10);
+        network.set_cost(4, 0, 10);
+        assert_update_matches_dijkstra(&network, &mut costs, vec![0], &[1, 4]);
+        assert_that!(costs[1], is(equal_to(Some(10))));
+        assert_that!(costs[15], is(equal_to(Some(15))));
+    }
+
+    #[test]
+    fn test_update_after_removal_disconnects() {
+        let mut network = get_grid();
+        let mut costs = network.dijkstra(vec![0]);
+        network.remove_edge(1, 0);
+        network.remove_edge(4, 0);
+        assert_update_matches_dijkstra(&network, &mut costs, vec![0], &[1, 4]);
+        assert_that!(costs[0], is(equal_to(Some(0))));
+        assert_that!(costs[5], is(equal_to(None)));
+    }
+
+    #[test]
+    fn test_update_after_addition_connects() {
+        let edges = vec![Edge::new(0, 1, 1), Edge::new(2, 3, 1)];
+        let mut network = Network::new(4, &edges);
+        let mut costs = network.dijkstra(vec![3]);
+        network.add_edge(Edge::new(1, 2, 1));
+        assert_update_matches_dijkstra(&network, &mut costs, vec![3], &[1]);
+        assert_that!(costs[0], is(equal_to(Some(3))));
+    }
+
+    #[test]
+    fn test_update_with_multiple_targets() {
+        let mut network = get_grid();
+        let mut costs = network.dijkstra(vec![0, 15]);
+        network.set_cost(14, 15, 5);
+        network.set_cost(11, 15, 5);
+        network.set_cost(1, 0, 3);
+        assert_update_matches_dijkstra(&network, &mut costs, vec![0, 15], &[14, 11, 1]);
+    }
+
+    #[test]
+    fn test_update_after_random_changes() {
+        let mut rng = StdRng::from_seed(&[12345]);
+        let edges = Edge::create_grid(8, 8, 1, Edge::<u32>::create_4_neighbour_deltas());
+        let mut network = Network::new(64, &edges);
+        let targets = vec![9, 50];
+        let mut costs = network.dijkstra(targets.clone());
+        for _ in 0..200 {
+            let from = rng.gen_range(0, 64);
+            let to = rng.gen_range(0, 64);
+            match rng.gen_range(0, 3) {
+                0 => network.add_edge(Edge::new(from, to, rng.gen_range(1, 11))),
+                1 => {
+                    let to = network.get_out(from).first().map(|e| e.to);
+                    if let Some(to) = to {
+                        network.remove_edge(from, to);
+                    }
+                },
+                _ => {
+                    let to = network.get_out(from).last().map(|e| e.to);
+                    if let Some(to) = to {
+                        network.set_cost(from, to, rng.gen_range(1, 11));
+                    }
+                },
+            }
+            assert_update_matches_dijkstra(&network, &mut costs, targets.clone(), &[from]);
+        }
+    }
+
+}
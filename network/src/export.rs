@@ -0,0 +1,157 @@
+use std::fmt::Display;
+use std::io::{self, Write};
+use {Cost, Network};
+
+// Node attributes are supplied by the caller as (name, value) pairs, e.g. the cell coordinates of each node
+pub type Attributes = Vec<(&'static str, String)>;
+
+impl <C: Cost + Display> Network<C> {
+
+    pub fn write_dot<W: Write, F: Fn(usize) -> Attributes>(&self, out: &mut W, attributes: F) -> io::Result<()> {
+        writeln!(out, "digraph network {{")?;
+        for node in 0..self.nodes {
+            let attributes: Vec<String> = attributes(node).iter()
+                .map(|(name, value)| format!("{}=\"{}\"", name, value.replace('"', "\\\"")))
+                .collect();
+            writeln!(out, "  {} [{}];", node, attributes.join(", "))?;
+        }
+        for edge in self.edges() {
+            writeln!(out, "  {} -> {} [cost=\"{}\"];", edge.from, edge.to, edge.cost)?;
+        }
+        writeln!(out, "}}")
+    }
+
+    pub fn write_graphml<W: Write, F: Fn(usize) -> Attributes>(&self, out: &mut W, attributes: F) -> io::Result<()> {
+        let attributes: Vec<Attributes> = (0..self.nodes).map(attributes).collect();
+        let mut names: Vec<&'static str> = vec![];
+        for &(name, _) in attributes.iter().flat_map(|a| a.iter()) {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+
+        writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+        writeln!(out, "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">")?;
+        for name in names.iter() {
+            writeln!(out, "  <key id=\"{}\" for=\"node\" attr.name=\"{}\" attr.type=\"string\"/>", name, name)?;
+        }
+        writeln!(out, "  <key id=\"cost\" for=\"edge\" attr.name=\"cost\" attr.type=\"string\"/>")?;
+        writeln!(out, "  <graph id=\"network\" edgedefault=\"directed\">")?;
+        for (node, attributes) in attributes.iter().enumerate() {
+            writeln!(out, "    <node id=\"n{}\">", node)?;
+            for (name, value) in attributes.iter() {
+                writeln!(out, "      <data key=\"{}\">{}</data>", name, escape_xml(value))?;
+            }
+            writeln!(out, "    </node>")?;
+        }
+        for edge in self.edges() {
+            writeln!(out, "    <edge source=\"n{}\" target=\"n{}\">", edge.from, edge.to)?;
+            writeln!(out, "      <data key=\"cost\">{}</data>", edge.cost)?;
+            writeln!(out, "    </edge>")?;
+        }
+        writeln!(out, "  </graph>")?;
+        writeln!(out, "</graphml>")
+    }
+
+    pub fn write_edges_csv<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "from,to,cost")?;
+        for edge in self.edges() {
+            writeln!(out, "{},{},{}", edge.from, edge.to, edge.cost)?;
+        }
+        Ok(())
+    }
+
+    // One row per node and one column per cost field, left empty where the node has no route
+    pub fn write_costs_csv<W: Write, F: Fn(usize) -> Attributes>(&self, out: &mut W, costs: &[Vec<Option<C>>], attributes: F) -> io::Result<()> {
+        let attributes: Vec<Attributes> = (0..self.nodes).map(attributes).collect();
+        let mut header = vec!["node".to_string()];
+        if let Some(first) = attributes.first() {
+            header.extend(first.iter().map(|&(name, _)| name.to_string()));
+        }
+        header.extend((0..costs.len()).map(|c| format!("cost_{}", c)));
+        writeln!(out, "{}", header.join(","))?;
+
+        for (node, attributes) in attributes.iter().enumerate() {
+            let mut row = vec![node.to_string()];
+            row.extend(attributes.iter().map(|(_, value)| escape_csv(value)));
+            row.extend(costs.iter().map(|c| c[node].map(|c| c.to_string()).unwrap_or_default()));
+            writeln!(out, "{}", row.join(","))?;
+        }
+        Ok(())
+    }
+
+}
+
+fn escape_xml(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn escape_csv(value: &str) -> String {
+    if value.contains(',') || value.contains('"') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use hamcrest::prelude::*;
+    use export::Attributes;
+    use {Edge, Network, OrderedFloat};
+
+    fn get_test_network() -> Network<u32> {
+        Network::new(3, &vec![Edge::new(0, 1, 2), Edge::new(1, 2, 3)])
+    }
+
+    fn attributes(node: usize) -> Attributes {
+        vec![("x", node.to_string()), ("label", format!("<{}>", node))]
+    }
+
+    fn to_string<F: Fn(&mut Vec<u8>)>(write: F) -> String {
+        let mut out = vec![];
+        write(&mut out);
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_write_dot() {
+        let network = get_test_network();
+        let dot = to_string(|out| network.write_dot(out, attributes).unwrap());
+        let expected = "digraph network {\n  0 [x=\"0\", label=\"<0>\"];\n  1 [x=\"1\", label=\"<1>\"];\n  2 [x=\"2\", label=\"<2>\"];\n  0 -> 1 [cost=\"2\"];\n  1 -> 2 [cost=\"3\"];\n}\n";
+        assert_that!(&dot[..], is(equal_to(expected)));
+    }
+
+    #[test]
+    fn test_write_graphml() {
+        let network = get_test_network();
+        let graphml = to_string(|out| network.write_graphml(out, attributes).unwrap());
+        assert!(graphml.contains("  <key id=\"x\" for=\"node\" attr.name=\"x\" attr.type=\"string\"/>\n"));
+        assert!(graphml.contains("    <node id=\"n2\">\n      <data key=\"x\">2</data>\n      <data key=\"label\">&lt;2&gt;</data>\n    </node>\n"));
+        assert!(graphml.contains("    <edge source=\"n1\" target=\"n2\">\n      <data key=\"cost\">3</data>\n    </edge>\n"));
+    }
+
+    #[test]
+    fn test_write_edges_csv() {
+        let network = get_test_network();
+        let csv = to_string(|out| network.write_edges_csv(out).unwrap());
+        assert_that!(&csv[..], is(equal_to("from,to,cost\n0,1,2\n1,2,3\n")));
+    }
+
+    #[test]
+    fn test_write_costs_csv() {
+        let network = get_test_network();
+        let costs = vec![network.dijkstra(vec![2]), network.dijkstra(vec![1])];
+        let csv = to_string(|out| network.write_costs_csv(out, &costs, |n| vec![("name", format!("a,{}", n))]).unwrap());
+        assert_that!(&csv[..], is(equal_to("node,name,cost_0,cost_1\n0,\"a,0\",5,2\n1,\"a,1\",3,0\n2,\"a,2\",0,\n")));
+    }
+
+    #[test]
+    fn test_write_float_costs() {
+        let network = Network::new(2, &vec![Edge::new(0, 1, OrderedFloat(0.5))]);
+        let csv = to_string(|out| network.write_edges_csv(out).unwrap());
+        assert_that!(&csv[..], is(equal_to("from,to,cost\n0,1,0.5\n")));
+    }
+
+}
@@ -0,0 +1,213 @@
+use std::collections::VecDeque;
+use {Cost, Network};
+
+#[derive(Debug, PartialEq)]
+pub struct MinCut {
+    pub flow: usize,
+    pub groups: Vec<usize>,
+}
+
+struct Arc {
+    to: usize,
+    capacity: usize,
+}
+
+// Dinic's algorithm. Arcs are stored in pairs so that an arc's reverse is at index ^ 1.
+struct FlowGraph {
+    arcs: Vec<Arc>,
+    adjacency: Vec<Vec<usize>>,
+}
+
+impl FlowGraph {
+
+    fn new(nodes: usize) -> FlowGraph {
+        FlowGraph{ arcs: vec![], adjacency: vec![vec![]; nodes] }
+    }
+
+    fn add_arc(&mut self, from: usize, to: usize, capacity: usize) {
+        self.adjacency[from].push(self.arcs.len());
+        self.arcs.push(Arc{ to, capacity });
+        self.adjacency[to].push(self.arcs.len());
+        self.arcs.push(Arc{ to: from, capacity: 0 });
+    }
+
+    fn levels(&self, source: usize) -> Vec<Option<usize>> {
+        let mut levels = vec![None; self.adjacency.len()];
+        let mut queue = VecDeque::new();
+        levels[source] = Some(0);
+        queue.push_back(source);
+        while let Some(node) = queue.pop_front() {
+            for arc in self.adjacency[node].iter() {
+                let arc = &self.arcs[*arc];
+                if arc.capacity > 0 && levels[arc.to].is_none() {
+                    levels[arc.to] = Some(levels[node].unwrap() + 1);
+                    queue.push_back(arc.to);
+                }
+            }
+        }
+        levels
+    }
+
+    // Pushes flow along one path of increasing levels from the source to the sink, returning how much. The path is
+    // kept on an explicit stack of arcs rather than by recursion, as it can be as long as the network is large.
+    fn augment(&mut self, source: usize, sink: usize, levels: &[Option<usize>], next: &mut [usize]) -> usize {
+        let mut path: Vec<usize> = vec![];
+        let mut node = source;
+        loop {
+            if node == sink {
+                let pushed = path.iter().map(|a| self.arcs[*a].capacity).min().unwrap_or(usize::MAX);
+                for index in path {
+                    self.arcs[index].capacity -= pushed;
+                    self.arcs[index ^ 1].capacity += pushed;
+                }
+                return pushed;
+            }
+            let mut advanced = false;
+            while next[node] < self.adjacency[node].len() {
+                let index = self.adjacency[node][next[node]];
+                let to = self.arcs[index].to;
+                if self.arcs[index].capacity > 0 && levels[to] == levels[node].map(|l| l + 1) {
+                    path.push(index);
+                    node = to;
+                    advanced = true;
+                    break;
+                }
+                next[node] += 1;
+            }
+            if !advanced {
+                // A dead end, so step back and never try the arc into it again
+                match path.pop() {
+                    Some(index) => {
+                        node = self.arcs[index ^ 1].to;
+                        next[node] += 1;
+                    },
+                    None => return 0,
+                }
+            }
+        }
+    }
+
+    fn max_flow(&mut self, source: usize, sink: usize) -> usize {
+        let mut flow = 0;
+        loop {
+            let levels = self.levels(source);
+            if levels[sink].is_none() {
+                return flow;
+            }
+            let mut next = vec![0; self.adjacency.len()];
+            loop {
+                let pushed = self.augment(source, sink, &levels, &mut next);
+                if pushed == 0 {
+                    break;
+                }
+                flow += pushed;
+            }
+        }
+    }
+
+}
+
+impl <C: Cost> Network<C> {
+
+    // Each node belongs to a group (e.g. all of the nodes in a cell) which can carry one unit of flow.
+    // Returns the maximum flow from the sources to the destinations and the groups making up the minimum cut
+    // closest to the sources.
+    pub fn min_cut(&self, sources: &[usize], destinations: &[usize], groups: &[usize]) -> MinCut {
+        let group_count = groups.iter().max().map(|g| g + 1).unwrap_or(0);
+        let unlimited = group_count + 1;
+        let group_in = |g: usize| 2 * g;
+        let group_out = |g: usize| 2 * g + 1;
+        let source = 2 * group_count;
+        let sink = source + 1;
+
+        let mut graph = FlowGraph::new(sink + 1);
+        for group in 0..group_count {
+            graph.add_arc(group_in(group), group_out(group), 1);
+        }
+        for edge in self.edges() {
+            if groups[edge.from] != groups[edge.to] {
+                graph.add_arc(group_out(groups[edge.from]), group_in(groups[edge.to]), unlimited);
+            }
+        }
+        for node in sources {
+            graph.add_arc(source, group_in(groups[*node]), unlimited);
+        }
+        for node in destinations {
+            graph.add_arc(group_out(groups[*node]), sink, unlimited);
+        }
+
+        let flow = graph.max_flow(source, sink);
+
+        let reachable = graph.levels(source);
+        let cut = (0..group_count)
+            .filter(|g| reachable[group_in(*g)].is_some() && reachable[group_out(*g)].is_none())
+            .collect();
+
+        MinCut{ flow, groups: cut }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use hamcrest::prelude::*;
+    use flow::MinCut;
+    use {Edge, Network};
+
+    #[test]
+    fn test_min_cut_on_grid_with_bottleneck() {
+        // 5 x 3 grid with the middle column blocked apart from its centre
+        let edges: Vec<Edge<u32>> = Edge::create_grid(5, 3, 1, Edge::<u32>::create_4_neighbour_deltas()).into_iter()
+            .filter(|e| ![2, 12].contains(&e.from) && ![2, 12].contains(&e.to))
+            .collect();
+        let network = Network::new(15, &edges);
+        let groups: Vec<usize> = (0..15).collect();
+        let cut = network.min_cut(&[0, 5, 10], &[4, 9, 14], &groups);
+        assert_that!(cut, is(equal_to(MinCut{ flow: 1, groups: vec![6] })));
+    }
+
+    #[test]
+    fn test_min_cut_with_parallel_routes() {
+        let edges = Edge::create_grid(5, 3, 1, Edge::<u32>::create_4_neighbour_deltas());
+        let network = Network::new(15, &edges);
+        let groups: Vec<usize> = (0..15).collect();
+        let cut = network.min_cut(&[0, 5, 10], &[4, 9, 14], &groups);
+        assert_that!(cut.flow, is(equal_to(3)));
+        assert_that!(cut.groups.len(), is(equal_to(3)));
+    }
+
+    #[test]
+    fn test_min_cut_uses_groups() {
+        // Two parallel lanes through the same cells only carry one unit of flow
+        let edges = vec![
+            Edge::new(0, 2, 1),
+            Edge::new(1, 3, 1),
+            Edge::new(2, 4, 1),
+            Edge::new(3, 5, 1)];
+        let network = Network::new(6, &edges);
+        let groups = vec![0, 0, 1, 1, 2, 2];
+        let cut = network.min_cut(&[0, 1], &[4, 5], &groups);
+        assert_that!(cut.flow, is(equal_to(1)));
+    }
+
+    #[test]
+    fn test_min_cut_on_long_path() {
+        // Long enough that a recursive search for augmenting paths would overflow the stack
+        let nodes = 200000;
+        let edges: Vec<Edge<u32>> = (0..nodes - 1).map(|n| Edge::new(n, n + 1, 1)).collect();
+        let network = Network::new(nodes, &edges);
+        let groups: Vec<usize> = (0..nodes).collect();
+        let cut = network.min_cut(&[0], &[nodes - 1], &groups);
+        assert_that!(cut, is(equal_to(MinCut{ flow: 1, groups: vec![0] })));
+    }
+
+    #[test]
+    fn test_min_cut_without_route() {
+        let edges = vec![Edge::new(0, 1, 1)];
+        let network = Network::new(3, &edges);
+        let cut = network.min_cut(&[0], &[2], &[0, 1, 2]);
+        assert_that!(cut, is(equal_to(MinCut{ flow: 0, groups: vec![] })));
+    }
+
+}
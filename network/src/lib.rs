@@ -1,16 +1,31 @@
 extern crate num;
 #[cfg(test)] #[macro_use] extern crate hamcrest;
+#[cfg(test)] extern crate rand;
+
+pub mod analysis;
+pub mod betweenness;
+pub mod cache;
+pub mod contraction;
+pub mod cost;
+pub mod dynamic;
+pub mod export;
+pub mod flow;
+pub mod parallel;
+pub mod paths;
+pub mod time_dependent;
+
+pub use cost::{Cost, OrderedFloat};
 
 #[derive(PartialEq, Debug, Clone)]
-pub struct Edge {
+pub struct Edge<C> {
     pub from: usize,
     pub to: usize,
-    pub cost: u8,
+    pub cost: C,
 }
 
-impl Edge {
+impl <C: Cost> Edge<C> {
 
-    pub fn new(from: usize, to: usize, cost: u8) -> Edge {
+    pub fn new(from: usize, to: usize, cost: C) -> Edge<C> {
         Edge {
             from,
             to,
@@ -20,18 +35,18 @@ impl Edge {
     pub fn create_4_neighbour_deltas() -> Vec<(usize, usize)> {
         vec![(1, 0), (0, 1)]
     }
-    
+
     pub fn create_8_neighbour_deltas() -> Vec<(usize, usize)> {
         vec![(1, 0), (1, 1), (0, 1)]
     }
 
-    pub fn create_grid(width: usize, height: usize, cost: u8, neighbour_deltas: Vec<(usize, usize)>) -> Vec<Edge> {
+    pub fn create_grid(width: usize, height: usize, cost: C, neighbour_deltas: Vec<(usize, usize)>) -> Vec<Edge<C>> {
 
         fn get_index(x: usize, y: usize, width: usize) -> usize {
             (y * width) + x
         }
 
-        fn create_edge(x: usize, y: usize, width: usize, height: usize, delta: &(usize, usize), cost: u8) -> Vec<Edge> {
+        fn create_edge<C: Cost>(x: usize, y: usize, width: usize, height: usize, delta: &(usize, usize), cost: C) -> Vec<Edge<C>> {
             let x_b = x + delta.0;
             let y_b = y + delta.1;
             if (x_b >= width) || (y_b >= height) {
@@ -52,82 +67,163 @@ impl Edge {
 
 }
 
-pub struct Network {
+pub struct Network<C> {
     pub nodes: usize,
-    edges_out: Vec<Vec<Edge>>,
-    edges_in: Vec<Vec<Edge>>,
+    out_offsets: Vec<usize>,
+    out_edges: Vec<Edge<C>>,
+    in_offsets: Vec<usize>,
+    in_edges: Vec<Edge<C>>,
 }
 
-impl Network {
-
-    pub fn new(nodes: usize, edges: &Vec<Edge>) -> Network {
+impl <C: Cost> Network<C> {
 
-        let mut out = Network {
+    pub fn new(nodes: usize, edges: &Vec<Edge<C>>) -> Network<C> {
+        let (out_offsets, out_edges) = Network::compress(nodes, edges, |e| e.from);
+        let (in_offsets, in_edges) = Network::compress(nodes, edges, |e| e.to);
+        Network {
            nodes,
-           edges_out: Vec::with_capacity(nodes),
-           edges_in: Vec::with_capacity(nodes),
-        };
+           out_offsets,
+           out_edges,
+           in_offsets,
+           in_edges,
+        }
+    }
+
+    fn compress<F>(nodes: usize, edges: &Vec<Edge<C>>, key: F) -> (Vec<usize>, Vec<Edge<C>>)
+        where F: Fn(&Edge<C>) -> usize {
+        let mut offsets = vec![0; nodes + 1];
+        for edge in edges {
+            offsets[key(edge) + 1] += 1;
+        }
+        for node in 0..nodes {
+            offsets[node + 1] += offsets[node];
+        }
+        let mut sorted = edges.clone();
+        sorted.sort_by_key(|e| key(e));
+        (offsets, sorted)
+    }
 
-        out.calculate_all_edges_in_and_out(edges);
+    pub fn get_in(&self, node: usize) -> &[Edge<C>] {
+        &self.in_edges[self.in_offsets[node]..self.in_offsets[node + 1]]
+    }
 
-        out
+    pub fn get_out(&self, node: usize) -> &[Edge<C>] {
+        &self.out_edges[self.out_offsets[node]..self.out_offsets[node + 1]]
+    }
+
+    pub fn edges(&self) -> &[Edge<C>] {
+        &self.out_edges
     }
 
-    fn calculate_all_edges_in_and_out(&mut self, edges: &Vec<Edge>) {
-        self.edges_out = Vec::with_capacity(self.nodes);
-        self.edges_in = Vec::with_capacity(self.nodes);
-        for _ in 0..self.nodes {
-            self.edges_out.push(vec![]);
-            self.edges_in.push(vec![]);
+    // add_edge and remove_edge shift every edge and offset after the change, so each is O(E). Use apply_edits for
+    // more than a few changes.
+    pub fn add_edge(&mut self, edge: Edge<C>) {
+        let out_position = self.out_offsets[edge.from + 1];
+        let in_position = self.in_offsets[edge.to + 1];
+        for offset in self.out_offsets[edge.from + 1..].iter_mut() {
+            *offset += 1;
         }
-        for edge in edges {
-            self.edges_out.get_mut(edge.from).unwrap().push(edge.clone());
-            self.edges_in.get_mut(edge.to).unwrap().push(edge.clone());
+        for offset in self.in_offsets[edge.to + 1..].iter_mut() {
+            *offset += 1;
+        }
+        self.out_edges.insert(out_position, edge.clone());
+        self.in_edges.insert(in_position, edge);
+    }
+
+    // Removes every edge from one node to the other, as set_cost updates every one, returning the edges removed
+    pub fn remove_edge(&mut self, from: usize, to: usize) -> Vec<Edge<C>> {
+        let out_positions: Vec<usize> = (self.out_offsets[from]..self.out_offsets[from + 1])
+            .filter(|i| self.out_edges[*i].to == to)
+            .collect();
+        let in_positions: Vec<usize> = (self.in_offsets[to]..self.in_offsets[to + 1])
+            .filter(|i| self.in_edges[*i].from == from)
+            .collect();
+        let removed: Vec<Edge<C>> = out_positions.iter().map(|i| self.out_edges[*i].clone()).collect();
+        for i in out_positions.iter().rev() {
+            self.out_edges.remove(*i);
+        }
+        for i in in_positions.iter().rev() {
+            self.in_edges.remove(*i);
+        }
+        for offset in self.out_offsets[from + 1..].iter_mut() {
+            *offset -= removed.len();
+        }
+        for offset in self.in_offsets[to + 1..].iter_mut() {
+            *offset -= removed.len();
         }
+        removed
     }
 
-    pub fn get_in(&self, node: usize) -> &Vec<Edge> {
-        &self.edges_in[node]
+    // Removes every edge between each pair of nodes, then adds the new edges, rebuilding the network once. Returns
+    // the edges removed, as remove_edge does.
+    pub fn apply_edits(&mut self, added: Vec<Edge<C>>, removed: &[(usize, usize)]) -> Vec<Edge<C>> {
+        use std::collections::HashSet;
+
+        let removed: HashSet<(usize, usize)> = removed.iter().cloned().collect();
+        let (removed, mut edges): (Vec<Edge<C>>, Vec<Edge<C>>) = self.out_edges.drain(..)
+            .partition(|e| removed.contains(&(e.from, e.to)));
+        edges.extend(added);
+        *self = Network::new(self.nodes, &edges);
+        removed
+    }
+
+    pub fn set_cost(&mut self, from: usize, to: usize, cost: C) -> bool {
+        let mut found = false;
+        for edge in self.out_edges[self.out_offsets[from]..self.out_offsets[from + 1]].iter_mut() {
+            if edge.to == to {
+                edge.cost = cost;
+                found = true;
+            }
+        }
+        for edge in self.in_edges[self.in_offsets[to]..self.in_offsets[to + 1]].iter_mut() {
+            if edge.from == from {
+                edge.cost = cost;
+            }
+        }
+        found
     }
 
-    pub fn get_out(&self, node: usize) -> &Vec<Edge> {
-        &self.edges_out[node]
+    pub fn memory_usage(&self) -> usize {
+        use std::mem::size_of;
+        (self.out_offsets.capacity() + self.in_offsets.capacity()) * size_of::<usize>() +
+            (self.out_edges.capacity() + self.in_edges.capacity()) * size_of::<Edge<C>>()
     }
 
-    pub fn dijkstra(&self, nodes: Vec<usize>) -> Vec<Option<u32>> {
+    pub fn dijkstra(&self, nodes: Vec<usize>) -> Vec<Option<C>> {
         use std::collections::BinaryHeap;
         use std::cmp::Ordering;
 
-        #[derive(Eq)]
-        struct Node {
+        struct Node<C> {
             index: usize,
-            cost: u32,
+            cost: C,
         }
 
-        impl Ord for Node {
-            fn cmp(&self, other: &Node) -> Ordering {
+        impl <C: Cost> Eq for Node<C> {}
+
+        impl <C: Cost> Ord for Node<C> {
+            fn cmp(&self, other: &Node<C>) -> Ordering {
                 self.cost.cmp(&other.cost).reverse()
             }
         }
 
-        impl PartialOrd for Node {
-            fn partial_cmp(&self, other: &Node) -> Option<Ordering> {
+        impl <C: Cost> PartialOrd for Node<C> {
+            fn partial_cmp(&self, other: &Node<C>) -> Option<Ordering> {
                 Some(self.cmp(other))
             }
         }
 
-        impl PartialEq for Node {
-            fn eq(&self, other: &Node) -> bool {
+        impl <C: Cost> PartialEq for Node<C> {
+            fn eq(&self, other: &Node<C>) -> bool {
                 self.cost == other.cost
             }
         }
 
         let mut closed: Vec<bool> = vec![false; self.nodes];
-        let mut out: Vec<Option<u32>> = vec![None; self.nodes];
+        let mut out: Vec<Option<C>> = vec![None; self.nodes];
         let mut heap = BinaryHeap::new();
 
         for node in nodes {
-            heap.push(Node{ index: node, cost: 0 });
+            heap.push(Node{ index: node, cost: C::zero() });
         }
 
         while let Some(Node {index, cost}) = heap.pop() {
@@ -137,7 +233,7 @@ impl Network {
 
                 for edge in self.get_in(index) {
                     if !closed[edge.from] {
-                        heap.push(Node{ index: edge.from, cost: cost + edge.cost as u32 });
+                        heap.push(Node{ index: edge.from, cost: cost + edge.cost });
                     }
                 }
             }
@@ -152,9 +248,9 @@ impl Network {
 mod tests {
 
     use hamcrest::prelude::*;
-    use {Edge, Network};
+    use {Edge, Network, OrderedFloat};
 
-    fn get_test_edges() -> Vec<Edge> {
+    fn get_test_edges() -> Vec<Edge<u32>> {
         vec![Edge::new(0, 1, 1),
             Edge::new(0, 2, 2),
             Edge::new(0, 2, 3),
@@ -167,7 +263,7 @@ mod tests {
             Edge::new(7, 7, 10)]
     }
 
-    fn get_test_network(edges: &Vec<Edge>) -> Network {
+    fn get_test_network(edges: &Vec<Edge<u32>>) -> Network<u32> {
         Network::new(8, edges)
     }
 
@@ -199,6 +295,75 @@ mod tests {
         assert_that!(&network.get_in(7).iter().collect(), contains(vec![&edges[9]]).exactly());
     }
 
+    #[test]
+    fn test_edges_grouped_by_from() {
+        let edges = get_test_edges();
+        let network = get_test_network(&edges);
+        let froms: Vec<usize> = network.edges().iter().map(|e| e.from).collect();
+        let expected = vec![0, 0, 0, 1, 2, 2, 5, 6, 6, 7];
+        assert_that!(&froms, is(equal_to(&expected)));
+    }
+
+    #[test]
+    fn test_add_edge() {
+        let edges = get_test_edges();
+        let mut network = get_test_network(&edges);
+        let added = Edge::new(4, 0, 11);
+        network.add_edge(added.clone());
+        assert_that!(&network.get_out(4).iter().collect(), contains(vec![&added]).exactly());
+        assert_that!(&network.get_in(0).iter().collect(), contains(vec![&added]).exactly());
+        assert_that!(&network.get_out(5).iter().collect(), contains(vec![&edges[6]]).exactly());
+        assert_that!(&network.get_in(5).iter().collect(), contains(vec![&edges[7], &edges[8]]).exactly());
+    }
+
+    #[test]
+    fn test_remove_edge() {
+        let edges = get_test_edges();
+        let mut network = get_test_network(&edges);
+        assert_that!(network.remove_edge(1, 3), is(equal_to(vec![edges[3].clone()])));
+        assert_that!(network.remove_edge(1, 3), is(equal_to(vec![])));
+        assert_that!(network.get_out(1).len(), is(equal_to(0)));
+        assert_that!(&network.get_in(3).iter().collect(), contains(vec![&edges[4], &edges[5]]).exactly());
+        assert_that!(&network.get_out(2).iter().collect(), contains(vec![&edges[4], &edges[5]]).exactly());
+    }
+
+    #[test]
+    fn test_remove_parallel_edges() {
+        let edges = get_test_edges();
+        let mut network = get_test_network(&edges);
+        assert_that!(network.remove_edge(2, 3), is(equal_to(vec![edges[4].clone(), edges[5].clone()])));
+        assert_that!(network.get_out(2).len(), is(equal_to(0)));
+        assert_that!(&network.get_in(3).iter().collect(), contains(vec![&edges[3]]).exactly());
+        assert_that!(&network.get_out(1).iter().collect(), contains(vec![&edges[3]]).exactly());
+    }
+
+    #[test]
+    fn test_apply_edits() {
+        let edges = get_test_edges();
+        let mut network = get_test_network(&edges);
+        let added = vec![Edge::new(4, 0, 11), Edge::new(2, 3, 1)];
+        let removed = network.apply_edits(added.clone(), &[(1, 3), (2, 3), (4, 1)]);
+        let expected = vec![edges[3].clone(), edges[4].clone(), edges[5].clone()];
+        assert_that!(&removed, is(equal_to(&expected)));
+        assert_that!(network.get_out(1).len(), is(equal_to(0)));
+        assert_that!(&network.get_out(2).iter().collect(), contains(vec![&added[1]]).exactly());
+        assert_that!(&network.get_in(3).iter().collect(), contains(vec![&added[1]]).exactly());
+        assert_that!(&network.get_out(4).iter().collect(), contains(vec![&added[0]]).exactly());
+        assert_that!(&network.get_in(0).iter().collect(), contains(vec![&added[0]]).exactly());
+        assert_that!(&network.get_in(5).iter().collect(), contains(vec![&edges[7], &edges[8]]).exactly());
+    }
+
+    #[test]
+    fn test_set_cost() {
+        let edges = get_test_edges();
+        let mut network = get_test_network(&edges);
+        let updated = Edge::new(0, 1, 12);
+        assert!(network.set_cost(0, 1, 12));
+        assert!(!network.set_cost(1, 0, 12));
+        assert_that!(&network.get_out(0).iter().collect(), contains(vec![&updated, &edges[1], &edges[2]]).exactly());
+        assert_that!(&network.get_in(1).iter().collect(), contains(vec![&updated]).exactly());
+    }
+
     #[test]
     fn test_create_grid() {
         
@@ -228,7 +393,7 @@ mod tests {
                                   Edge::new(8, 7, 1),
         ];
 
-        let edges = Edge::create_grid(3, 3, 1, Edge::create_4_neighbour_deltas());
+        let edges = Edge::create_grid(3, 3, 1, Edge::<u32>::create_4_neighbour_deltas());
         assert_that!(&edges.iter().collect(), contains(expected_edges.iter().collect()).exactly());
     }
 
@@ -254,7 +419,7 @@ mod tests {
 
     #[test]
     fn test_dijkstra_on_grid() {
-        let edges = Edge::create_grid(4, 4, 1, Edge::create_4_neighbour_deltas());
+        let edges = Edge::create_grid(4, 4, 1, Edge::<u32>::create_4_neighbour_deltas());
         let network = Network::new(16, &edges);
         let expected = vec![
            Some(0), Some(1), Some(2), Some(3),
@@ -268,7 +433,7 @@ mod tests {
 
     #[test]
     fn test_multi_destinations() {
-        let edges = Edge::create_grid(4, 4, 1, Edge::create_4_neighbour_deltas());
+        let edges = Edge::create_grid(4, 4, 1, Edge::<u32>::create_4_neighbour_deltas());
         let network = Network::new(16, &edges);
         let expected = vec![
            Some(0), Some(0), Some(0), Some(0),
@@ -279,6 +444,26 @@ mod tests {
         
         assert_that!(&network.dijkstra(vec![0, 1, 2, 3]), is(equal_to(&expected)));
     }
+
+    #[test]
+    fn test_dijkstra_with_wide_costs() {
+        let edges = vec![Edge::new(0, 1, 300u32),
+            Edge::new(1, 2, 70000),
+            Edge::new(0, 2, 80000)];
+        let network = Network::new(3, &edges);
+        let expected = vec![Some(70300), Some(70000), Some(0)];
+        assert_that!(&network.dijkstra(vec![2]), is(equal_to(&expected)));
+    }
+
+    #[test]
+    fn test_dijkstra_with_fractional_costs() {
+        let edges = vec![Edge::new(0, 1, OrderedFloat(0.25)),
+            Edge::new(1, 2, OrderedFloat(0.5)),
+            Edge::new(0, 2, OrderedFloat(1.0))];
+        let network = Network::new(3, &edges);
+        let expected = vec![Some(OrderedFloat(0.75)), Some(OrderedFloat(0.5)), Some(OrderedFloat(0.0))];
+        assert_that!(&network.dijkstra(vec![2]), is(equal_to(&expected)));
+    }
  
 }
 
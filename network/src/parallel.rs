@@ -0,0 +1,61 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use {Cost, Network};
+
+impl <C: Cost + Send + Sync> Network<C> {
+
+    pub fn dijkstra_all(&self, targets: Vec<Vec<usize>>, threads: usize) -> Vec<Vec<Option<C>>> {
+        let next = AtomicUsize::new(0);
+        let targets = &targets;
+        let next = &next;
+
+        let mut results: Vec<(usize, Vec<Option<C>>)> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..threads.max(1).min(targets.len()))
+                .map(|_| scope.spawn(move || {
+                    let mut out = vec![];
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        if index >= targets.len() {
+                            return out;
+                        }
+                        out.push((index, self.dijkstra(targets[index].clone())));
+                    }
+                }))
+                .collect();
+            handles.into_iter().flat_map(|h| h.join().unwrap()).collect()
+        });
+
+        results.sort_by_key(|r| r.0);
+        results.into_iter().map(|r| r.1).collect()
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use hamcrest::prelude::*;
+    use {Edge, Network};
+
+    fn get_targets() -> Vec<Vec<usize>> {
+        vec![vec![0], vec![5, 6], vec![15], vec![3, 12], vec![9]]
+    }
+
+    #[test]
+    fn test_dijkstra_all_matches_dijkstra() {
+        let edges = Edge::create_grid(4, 4, 1, Edge::<u32>::create_4_neighbour_deltas());
+        let network = Network::new(16, &edges);
+        let expected: Vec<Vec<Option<u32>>> = get_targets().into_iter().map(|t| network.dijkstra(t)).collect();
+        for threads in 1..7 {
+            assert_that!(&network.dijkstra_all(get_targets(), threads), is(equal_to(&expected)));
+        }
+    }
+
+    #[test]
+    fn test_dijkstra_all_without_targets() {
+        let edges = Edge::create_grid(4, 4, 1, Edge::<u32>::create_4_neighbour_deltas());
+        let network = Network::new(16, &edges);
+        assert_that!(network.dijkstra_all(vec![], 4).len(), is(equal_to(0)));
+    }
+
+}
@@ -0,0 +1,279 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+use {Cost, Network};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Path<C> {
+    pub nodes: Vec<usize>,
+    pub cost: C,
+}
+
+impl <C: Cost> Path<C> {
+
+    // Fraction of this path's edges which are also used by the other path
+    pub fn overlap(&self, other: &Path<C>) -> f64 {
+        if self.nodes.len() < 2 {
+            return 1.0;
+        }
+        let others: HashSet<(usize, usize)> = other.nodes.windows(2).map(|w| (w[0], w[1])).collect();
+        let shared = self.nodes.windows(2).filter(|w| others.contains(&(w[0], w[1]))).count();
+        shared as f64 / (self.nodes.len() - 1) as f64
+    }
+
+}
+
+// Loopless paths in increasing order of cost, using Yen's algorithm
+pub struct ShortestPaths<'a, C: 'a> {
+    network: &'a Network<C>,
+    to: usize,
+    found: Vec<Path<C>>,
+    candidates: Vec<Path<C>>,
+    first: Option<Path<C>>,
+}
+
+impl <'a, C: Cost> Iterator for ShortestPaths<'a, C> {
+    type Item = Path<C>;
+
+    fn next(&mut self) -> Option<Path<C>> {
+        if let Some(first) = self.first.take() {
+            self.found.push(first.clone());
+            return Some(first);
+        }
+        if let Some(last) = self.found.last().cloned() {
+            self.add_candidates(&last);
+        }
+        let best = (0..self.candidates.len()).min_by_key(|c| (self.candidates[*c].cost, self.candidates[*c].nodes.len()))?;
+        let path = self.candidates.swap_remove(best);
+        self.found.push(path.clone());
+        Some(path)
+    }
+}
+
+impl <'a, C: Cost> ShortestPaths<'a, C> {
+
+    fn add_candidates(&mut self, last: &Path<C>) {
+        let network = self.network;
+        let mut banned_nodes = vec![false; network.nodes];
+        let mut root_cost = C::zero();
+        for i in 0..last.nodes.len() - 1 {
+            let spur = last.nodes[i];
+            let root = &last.nodes[..i + 1];
+
+            let mut banned_edges = vec![false; network.out_edges.len()];
+            for path in self.found.iter().filter(|p| p.nodes.len() > i + 1 && p.nodes[..i + 1] == *root) {
+                let first_edge = network.out_offsets[spur];
+                for (offset, edge) in network.get_out(spur).iter().enumerate() {
+                    if edge.to == path.nodes[i + 1] {
+                        banned_edges[first_edge + offset] = true;
+                    }
+                }
+            }
+
+            if let Some(spur_path) = network.restricted_path(spur, self.to, &banned_nodes, &banned_edges) {
+                let mut nodes = root[..i].to_vec();
+                nodes.extend(spur_path.nodes);
+                let path = Path{ nodes, cost: root_cost + spur_path.cost };
+                if !self.candidates.contains(&path) && !self.found.contains(&path) {
+                    self.candidates.push(path);
+                }
+            }
+
+            banned_nodes[spur] = true;
+            root_cost = root_cost + network.edge_cost(spur, last.nodes[i + 1]);
+        }
+    }
+
+}
+
+impl <C: Cost> Network<C> {
+
+    pub fn shortest_path(&self, from: usize, to: usize) -> Option<Path<C>> {
+        self.restricted_path(from, to, &vec![false; self.nodes], &vec![false; self.out_edges.len()])
+    }
+
+    pub fn shortest_paths(&self, from: usize, to: usize) -> ShortestPaths<'_, C> {
+        ShortestPaths {
+            network: self,
+            to,
+            found: vec![],
+            candidates: vec![],
+            first: self.shortest_path(from, to),
+        }
+    }
+
+    pub fn k_shortest_paths(&self, from: usize, to: usize, k: usize) -> Vec<Path<C>> {
+        self.shortest_paths(from, to).take(k).collect()
+    }
+
+    // Up to k of the shortest paths, skipping any path whose overlap with an already chosen path is above
+    // max_overlap. At most max_candidates paths are considered.
+    pub fn dissimilar_paths(&self, from: usize, to: usize, k: usize, max_overlap: f64, max_candidates: usize) -> Vec<Path<C>> {
+        let mut out: Vec<Path<C>> = vec![];
+        for path in self.shortest_paths(from, to).take(max_candidates) {
+            if out.iter().all(|chosen| path.overlap(chosen) <= max_overlap) {
+                out.push(path);
+                if out.len() == k {
+                    break;
+                }
+            }
+        }
+        out
+    }
+
+    fn edge_cost(&self, from: usize, to: usize) -> C {
+        self.get_out(from).iter().filter(|e| e.to == to).map(|e| e.cost).min().unwrap()
+    }
+
+    // Forward dijkstra avoiding the banned nodes and edges (indexed as in Network::edges)
+    fn restricted_path(&self, from: usize, to: usize, banned_nodes: &[bool], banned_edges: &[bool]) -> Option<Path<C>> {
+        let mut closed = vec![false; self.nodes];
+        let mut previous: Vec<Option<usize>> = vec![None; self.nodes];
+        let mut heap = BinaryHeap::new();
+        heap.push(Reverse((C::zero(), from, from)));
+
+        while let Some(Reverse((cost, index, parent))) = heap.pop() {
+            if closed[index] {
+                continue;
+            }
+            closed[index] = true;
+            if index != from {
+                previous[index] = Some(parent);
+            }
+            if index == to {
+                let mut nodes = vec![to];
+                while let Some(parent) = previous[*nodes.last().unwrap()] {
+                    nodes.push(parent);
+                }
+                nodes.reverse();
+                return Some(Path{ nodes, cost });
+            }
+            let first_edge = self.out_offsets[index];
+            for (offset, edge) in self.get_out(index).iter().enumerate() {
+                if !closed[edge.to] && !banned_nodes[edge.to] && !banned_edges[first_edge + offset] {
+                    heap.push(Reverse((cost + edge.cost, edge.to, index)));
+                }
+            }
+        }
+        None
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use hamcrest::prelude::*;
+    use paths::Path;
+    use rand::{Rng, SeedableRng, StdRng};
+    use {Edge, Network};
+
+    fn get_test_network() -> Network<u32> {
+        // The classic example from Yen's paper, with C..H as 0..5
+        let edges = vec![
+            Edge::new(0, 1, 3),
+            Edge::new(0, 2, 2),
+            Edge::new(1, 3, 4),
+            Edge::new(2, 1, 1),
+            Edge::new(2, 3, 2),
+            Edge::new(2, 4, 3),
+            Edge::new(3, 4, 2),
+            Edge::new(3, 5, 1),
+            Edge::new(4, 5, 2)];
+        Network::new(6, &edges)
+    }
+
+    #[test]
+    fn test_shortest_path() {
+        let path = get_test_network().shortest_path(0, 5);
+        let expected = Some(Path{ nodes: vec![0, 2, 3, 5], cost: 5 });
+        assert_that!(&path, is(equal_to(&expected)));
+        assert_that!(get_test_network().shortest_path(5, 0), is(equal_to(None)));
+    }
+
+    #[test]
+    fn test_k_shortest_paths() {
+        let paths = get_test_network().k_shortest_paths(0, 5, 3);
+        let expected = vec![
+            Path{ nodes: vec![0, 2, 3, 5], cost: 5 },
+            Path{ nodes: vec![0, 2, 4, 5], cost: 7 }];
+        assert_that!(&paths[..2].to_vec(), is(equal_to(&expected)));
+        // Three paths cost 8
+        assert_that!(paths[2].cost, is(equal_to(8)));
+    }
+
+    #[test]
+    fn test_runs_out_of_paths() {
+        let paths = get_test_network().k_shortest_paths(0, 5, 100);
+        // Every loopless path from 0 to 5
+        assert_that!(paths.len(), is(equal_to(7)));
+    }
+
+    #[test]
+    fn test_against_all_loopless_paths() {
+        fn all_paths(network: &Network<u32>, path: &mut Vec<usize>, cost: u32, to: usize, out: &mut Vec<u32>) {
+            let node = *path.last().unwrap();
+            if node == to {
+                out.push(cost);
+                return;
+            }
+            let mut nexts: Vec<(usize, u32)> = vec![];
+            for edge in network.get_out(node) {
+                match nexts.iter_mut().find(|n| n.0 == edge.to) {
+                    Some(next) => next.1 = next.1.min(edge.cost),
+                    None => nexts.push((edge.to, edge.cost)),
+                }
+            }
+            for (next, edge_cost) in nexts {
+                if !path.contains(&next) {
+                    path.push(next);
+                    all_paths(network, path, cost + edge_cost, to, out);
+                    path.pop();
+                }
+            }
+        }
+
+        let mut rng = StdRng::from_seed(&[54321]);
+        for _ in 0..20 {
+            let edges: Vec<Edge<u32>> = (0..24)
+                .map(|_| Edge::new(rng.gen_range(0, 8), rng.gen_range(0, 8), rng.gen_range(1, 10)))
+                .filter(|e| e.from != e.to)
+                .collect();
+            let network = Network::new(8, &edges);
+            let mut expected = vec![];
+            all_paths(&network, &mut vec![0], 0, 7, &mut expected);
+            expected.sort();
+            let paths = network.k_shortest_paths(0, 7, 1000);
+            let costs: Vec<u32> = paths.iter().map(|p| p.cost).collect();
+            assert_that!(&costs, is(equal_to(&expected)));
+            for path in paths {
+                let mut nodes = path.nodes.clone();
+                nodes.sort();
+                nodes.dedup();
+                assert_that!(nodes.len(), is(equal_to(path.nodes.len())));
+            }
+        }
+    }
+
+    #[test]
+    fn test_dissimilar_paths() {
+        let edges = Edge::create_grid(4, 4, 1, Edge::<u32>::create_4_neighbour_deltas());
+        let network = Network::new(16, &edges);
+        let paths = network.dissimilar_paths(0, 15, 3, 0.5, 100);
+        assert_that!(paths.len(), is(equal_to(3)));
+        for a in paths.iter() {
+            assert_that!(a.cost, is(equal_to(6)));
+            for b in paths.iter().filter(|b| *b != a) {
+                assert!(a.overlap(b) <= 0.5);
+            }
+        }
+    }
+
+    #[test]
+    fn test_overlap() {
+        let a = Path{ nodes: vec![0, 1, 2, 3], cost: 3 };
+        let b = Path{ nodes: vec![0, 1, 4, 3], cost: 3 };
+        assert_that!(a.overlap(&b), is(equal_to(1.0 / 3.0)));
+        assert_that!(a.overlap(&a), is(equal_to(1.0)));
+    }
+
+}
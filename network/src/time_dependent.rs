@@ -0,0 +1,187 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use {Cost, Network};
+
+// A piecewise constant edge cost. Each piece applies from its start time until the next piece starts, and the edge's
+// own cost applies before the first piece. A cost of None closes the edge.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Schedule<C> {
+    pieces: Vec<(C, Option<C>)>,
+}
+
+impl <C: Cost> Schedule<C> {
+
+    pub fn new() -> Schedule<C> {
+        Schedule{ pieces: vec![] }
+    }
+
+    pub fn set(&mut self, start: C, cost: Option<C>) {
+        match self.pieces.binary_search_by_key(&start, |p| p.0) {
+            Ok(i) => self.pieces[i].1 = cost,
+            Err(i) => self.pieces.insert(i, (start, cost)),
+        }
+    }
+
+    pub fn cost_at(&self, base: C, time: C) -> Option<C> {
+        match self.pieces.iter().rposition(|p| p.0 <= time) {
+            Some(i) => self.pieces[i].1,
+            None => Some(base),
+        }
+    }
+
+    // The earliest arrival when setting off at departure, allowing for waiting until a later piece if that arrives
+    // sooner. This keeps arrivals FIFO: leaving later never means arriving earlier.
+    pub fn arrival(&self, base: C, departure: C) -> Option<C> {
+        let now = self.cost_at(base, departure).map(|c| departure + c);
+        let later = self.pieces.iter()
+            .filter(|p| p.0 > departure)
+            .filter_map(|p| p.1.map(|c| p.0 + c))
+            .min();
+        match (now, later) {
+            (Some(now), Some(later)) => Some(now.min(later)),
+            (now, later) => now.or(later),
+        }
+    }
+
+}
+
+// Schedules for edges between pairs of nodes. Edges without a schedule always cost their own cost.
+#[derive(Clone, Debug, Default)]
+pub struct Schedules<C> {
+    edges: HashMap<(usize, usize), Schedule<C>>,
+}
+
+impl <C: Cost> Schedules<C> {
+
+    pub fn new() -> Schedules<C> {
+        Schedules{ edges: HashMap::new() }
+    }
+
+    pub fn set(&mut self, from: usize, to: usize, schedule: Schedule<C>) {
+        self.edges.insert((from, to), schedule);
+    }
+
+    pub fn get(&self, from: usize, to: usize) -> Option<&Schedule<C>> {
+        self.edges.get(&(from, to))
+    }
+
+    pub fn arrival(&self, from: usize, to: usize, base: C, departure: C) -> Option<C> {
+        match self.get(from, to) {
+            Some(schedule) => schedule.arrival(base, departure),
+            None => Some(departure + base),
+        }
+    }
+
+}
+
+impl <C: Cost> Network<C> {
+
+    // Earliest arrival time at every node when leaving the sources at departure. Unlike dijkstra this searches
+    // forwards, as the cost of an edge depends on when it is reached.
+    pub fn time_dependent_dijkstra(&self, schedules: &Schedules<C>, sources: &[usize], departure: C) -> Vec<Option<C>> {
+        let mut out: Vec<Option<C>> = vec![None; self.nodes];
+        let mut heap = BinaryHeap::new();
+        for source in sources {
+            heap.push(Reverse((departure, *source)));
+        }
+
+        while let Some(Reverse((time, index))) = heap.pop() {
+            if out[index].is_some() {
+                continue;
+            }
+            out[index] = Some(time);
+            for edge in self.get_out(index) {
+                if out[edge.to].is_none() {
+                    if let Some(arrival) = schedules.arrival(edge.from, edge.to, edge.cost, time) {
+                        heap.push(Reverse((arrival, edge.to)));
+                    }
+                }
+            }
+        }
+
+        out
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use hamcrest::prelude::*;
+    use time_dependent::{Schedule, Schedules};
+    use {Edge, Network};
+
+    fn get_schedule(pieces: &[(u32, Option<u32>)]) -> Schedule<u32> {
+        let mut schedule = Schedule::new();
+        for &(start, cost) in pieces {
+            schedule.set(start, cost);
+        }
+        schedule
+    }
+
+    #[test]
+    fn test_cost_at() {
+        let schedule = get_schedule(&[(20, None), (10, Some(5))]);
+        assert_that!(schedule.cost_at(1, 0), is(equal_to(Some(1))));
+        assert_that!(schedule.cost_at(1, 10), is(equal_to(Some(5))));
+        assert_that!(schedule.cost_at(1, 19), is(equal_to(Some(5))));
+        assert_that!(schedule.cost_at(1, 25), is(equal_to(None)));
+    }
+
+    #[test]
+    fn test_arrival_waits_for_reopening() {
+        let schedule = get_schedule(&[(10, None), (20, Some(1))]);
+        assert_that!(schedule.arrival(1, 5), is(equal_to(Some(6))));
+        assert_that!(schedule.arrival(1, 12), is(equal_to(Some(21))));
+        let closed = get_schedule(&[(10, None)]);
+        assert_that!(closed.arrival(1, 12), is(equal_to(None)));
+    }
+
+    #[test]
+    fn test_arrival_is_fifo() {
+        let schedule = get_schedule(&[(10, Some(20)), (15, Some(2)), (30, None), (40, Some(3))]);
+        let mut last = 0;
+        for departure in 0..50 {
+            let arrival = schedule.arrival(1, departure).unwrap();
+            assert!(arrival >= last);
+            assert!(arrival > departure);
+            last = arrival;
+        }
+        assert_that!(schedule.arrival(1, 10), is(equal_to(Some(17))));
+    }
+
+    #[test]
+    fn test_without_schedules_matches_dijkstra() {
+        let edges = Edge::create_grid(5, 4, 2, Edge::<u32>::create_4_neighbour_deltas());
+        let network = Network::new(20, &edges);
+        let arrivals = network.time_dependent_dijkstra(&Schedules::new(), &[7], 100);
+        // The grid is symmetric, so the cost from 7 is the same as the cost to 7
+        let expected: Vec<Option<u32>> = network.dijkstra(vec![7]).into_iter().map(|c| c.map(|c| c + 100)).collect();
+        assert_that!(&arrivals, is(equal_to(&expected)));
+    }
+
+    #[test]
+    fn test_detour_around_slow_period() {
+        // 0 -> 1 -> 3 is shorter, unless 1 -> 3 is slow
+        let edges = vec![Edge::new(0, 1, 1), Edge::new(1, 3, 1), Edge::new(0, 2, 2), Edge::new(2, 3, 2)];
+        let network = Network::new(4, &edges);
+        let mut schedules = Schedules::new();
+        schedules.set(1, 3, get_schedule(&[(10, Some(10)), (20, Some(1))]));
+
+        assert_that!(network.time_dependent_dijkstra(&schedules, &[0], 0)[3], is(equal_to(Some(2))));
+        assert_that!(network.time_dependent_dijkstra(&schedules, &[0], 9)[3], is(equal_to(Some(13))));
+        // Waiting for the slow period to end beats the detour
+        assert_that!(network.time_dependent_dijkstra(&schedules, &[0], 18)[3], is(equal_to(Some(21))));
+    }
+
+    #[test]
+    fn test_closure() {
+        let edges = vec![Edge::new(0, 1, 1), Edge::new(1, 2, 1)];
+        let network = Network::new(3, &edges);
+        let mut schedules = Schedules::new();
+        schedules.set(1, 2, get_schedule(&[(0, None)]));
+        let expected = vec![Some(0), Some(1), None];
+        assert_that!(&network.time_dependent_dijkstra(&schedules, &[0], 0), is(equal_to(&expected)));
+    }
+
+}
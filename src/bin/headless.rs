@@ -0,0 +1,78 @@
+extern crate hanger_lane;
+extern crate clap;
+
+use std::fs::File;
+use std::io::prelude::*;
+use std::sync::Arc;
+use hanger_lane::setup::{add_simulation_args, get_matches, load_city, get_seed, create_occupancy, create_state, create_simulation};
+use clap::{App, Arg, ArgMatches};
+
+// Runs the simulation as fast as possible without a window, then prints statistics on the run
+fn main() {
+    let args = get_args();
+    let steps: usize = args.value_of("steps").unwrap().parse().unwrap();
+    let arrivals: Option<usize> = args.value_of("arrivals").map(|a| a.parse().unwrap());
+
+    let city = Arc::new(load_city(&args));
+    let mut occupancy = create_occupancy(&city, &args);
+    let seed = get_seed(&args);
+    let simulation = create_simulation(&city, &mut occupancy, &args);
+    let mut state = create_state(occupancy, seed, &args);
+    let mut trips = args.value_of("trips").map(|path| {
+        let mut f = File::create(path).expect("Failed to create file");
+        writeln!(f, "vehicle,origin,origin_group,end,destination_group,spawn_step,end_step,moves,waited,arrived").expect("Failed to write file");
+        f
+    });
+
+    while state.clock.get_step() < steps && !state.halted && arrivals.map_or(true, |a| state.statistics.arrived < a) {
+        state = simulation.step(state);
+        for event in state.events.drain(..) {
+            println!("{}", event);
+        }
+        if let Some(ref mut f) = trips {
+            for t in state.traffic.completed.iter() {
+                writeln!(f, "{},{},{},{},{},{},{},{},{},{}", t.vehicle, t.origin, t.origin_group,
+                         t.end, t.destination_index, t.spawn_step, t.end_step, t.moves, t.waited, t.arrived)
+                    .expect("Failed to write file");
+            }
+        }
+    }
+    if state.halted {
+        println!("Halted");
+    }
+
+    match args.value_of("json") {
+        Some(path) => {
+            let mut f = File::create(path).expect("Failed to create file");
+            f.write_all(state.statistics.to_json(&state.clock).as_bytes()).expect("Failed to write file");
+        },
+        None => {
+            println!("Steps: {} ({:.1} simulated seconds)", state.clock.get_step(), state.clock.get_seconds());
+            print!("{}", state.statistics);
+        },
+    }
+}
+
+fn get_args() -> ArgMatches<'static> {
+    let app = App::new("Hanger Lane Traffic Simulator (headless)")
+        .version("0.0.1")
+        .author("Thomas Elder <tgelder@gmail.com>");
+    let app = add_simulation_args(app)
+        .arg(Arg::with_name("steps")
+             .help("Stop after this many steps")
+             .long("steps")
+             .default_value("1000"))
+        .arg(Arg::with_name("arrivals")
+             .help("Stop once this many vehicles have reached their destinations")
+             .long("arrivals")
+             .takes_value(true))
+        .arg(Arg::with_name("trips")
+             .help("Write every trip to this .csv file when it ends, with the nodes and steps it started and ended at, how many steps it moved and waited, and whether it arrived or was despawned")
+             .long("trips")
+             .takes_value(true))
+        .arg(Arg::with_name("json")
+             .help("Write the statistics to this .json file instead of printing them")
+             .long("json")
+             .takes_value(true));
+    get_matches(app)
+}
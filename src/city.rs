@@ -1,5 +1,36 @@
+use std::collections::HashMap;
 use {Cell, Direction, DIRECTIONS, get_opposite};
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Movement {
+    Straight,
+    Left,
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct TurnPenalties {
+    pub straight: u32,
+    pub left: u32,
+    pub right: u32,
+}
+
+impl TurnPenalties {
+
+    pub fn new(straight: u32, left: u32, right: u32) -> TurnPenalties {
+        TurnPenalties{ straight, left, right }
+    }
+
+    pub fn get(&self, movement: Movement) -> u32 {
+        match movement {
+            Movement::Straight => self.straight,
+            Movement::Left => self.left,
+            Movement::Right => self.right,
+        }
+    }
+
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct Road {
     x: usize,
@@ -22,6 +53,16 @@ impl Road {
         Cell{x: self.x, y: self.y, d: self.exit}
     }
 
+    pub fn get_movement(&self) -> Movement {
+        let entry = DIRECTIONS.iter().position(|d| *d == self.entry).unwrap();
+        let exit = DIRECTIONS.iter().position(|d| *d == self.exit).unwrap();
+        match (exit + 4 - entry) % 4 {
+            1 => Movement::Right,
+            3 => Movement::Left,
+            _ => Movement::Straight,
+        }
+    }
+
 }
 
 #[derive(Clone, Debug)]
@@ -33,13 +74,18 @@ pub struct City {
     pub sources: Vec<Vec<usize>>,
     pub destinations: Vec<Vec<usize>>,
     pub lights: Vec<Vec<usize>>,
+    pub turn_penalties: TurnPenalties,
+    pub cell_penalties: HashMap<(usize, usize, Movement), u32>,
+    pub banned_turns: Vec<Road>,
+    pub capacities: HashMap<(usize, usize), usize>,
+    pub conflict_groups: HashMap<usize, usize>,
 }
 
 use network::Edge;
 
 impl City {
     pub fn new(width: usize, height: usize) -> City {
-        City{ id: 0, width, height, roads: vec![], sources: vec![], destinations: vec![], lights: vec![] }
+        City::with_roads(width, height, vec![])
     }
 
     pub fn _with_all_roads(width: usize, height: usize) -> City {
@@ -56,7 +102,58 @@ impl City {
             }
         }
 
-        City { id: 0, width, height, roads, sources: vec![], destinations: vec![], lights: vec![] }
+        City::with_roads(width, height, roads)
+    }
+
+    fn with_roads(width: usize, height: usize, roads: Vec<Road>) -> City {
+        City {
+            id: 0,
+            width,
+            height,
+            roads,
+            sources: vec![],
+            destinations: vec![],
+            lights: vec![],
+            turn_penalties: TurnPenalties::default(),
+            cell_penalties: HashMap::new(),
+            banned_turns: vec![],
+            capacities: HashMap::new(),
+            conflict_groups: HashMap::new(),
+        }
+    }
+
+    // Penalties set on a cell override the city wide penalty for that movement
+    pub fn get_penalty(&self, road: &Road) -> u32 {
+        let movement = road.get_movement();
+        match self.cell_penalties.get(&(road.x, road.y, movement)) {
+            Some(penalty) => *penalty,
+            None => self.turn_penalties.get(movement),
+        }
+    }
+
+    // The number of vehicles which fit in the cell of a node at once
+    pub fn get_capacity(&self, index: usize) -> usize {
+        let cell = self.get_cell(index);
+        *self.capacities.get(&(cell.x, cell.y)).unwrap_or(&1)
+    }
+
+    // For every node, the nodes locked along with it: the other members of its conflict group or, for nodes without
+    // a group, the other nodes of its cell without a group. Group numbers only apply within a cell.
+    pub fn get_conflict_groups(&self) -> Vec<Vec<usize>> {
+        let mut groups: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        for (node, group) in self.conflict_groups.iter() {
+            groups.entry((node / 4, *group)).or_default().push(*node);
+        }
+        for members in groups.values_mut() {
+            members.sort();
+        }
+        (0..self.get_num_nodes()).map(|node| match self.conflict_groups.get(&node) {
+            Some(group) => groups[&(node / 4, *group)].clone(),
+            None => {
+                let start = 4 * (node / 4);
+                (start..start + 4).filter(|n| !self.conflict_groups.contains_key(n)).collect()
+            },
+        }).collect()
     }
 
     fn forward(&self, &Cell{ref x, ref y, ref d}: &Cell) -> Option<Cell> {
@@ -96,11 +193,11 @@ impl City {
 		self.width * self.height * 4
 	}
 
-	pub fn create_edges(&self) -> Vec<Edge> {
+	pub fn create_edges(&self) -> Vec<Edge<u32>> {
         let mut out = vec![];
-        for road in self.roads.iter() {
+        for road in self.roads.iter().filter(|r| !self.banned_turns.contains(r)) {
             if let Some(forward) = self.forward(&road.get_exit()) {
-                out.push(Edge::new(self.get_index(&road.get_start()), self.get_index(&forward), 1));
+                out.push(Edge::new(self.get_index(&road.get_start()), self.get_index(&forward), 1 + self.get_penalty(road)));
             }
         }
         out
@@ -111,7 +208,7 @@ impl City {
 mod tests {
 
     use {Cell, Direction, DIRECTIONS};
-    use city::{Road, City};
+    use city::{Road, City, Movement, TurnPenalties};
     use network::Edge;
     use hamcrest::prelude::*;
 
@@ -172,6 +269,41 @@ mod tests {
 
     }
 
+    #[test]
+    fn test_get_movement() {
+        assert_that!(Road::new(0, 0, Direction::North, Direction::North).get_movement(), is(equal_to(Movement::Straight)));
+        assert_that!(Road::new(0, 0, Direction::North, Direction::East).get_movement(), is(equal_to(Movement::Right)));
+        assert_that!(Road::new(0, 0, Direction::North, Direction::West).get_movement(), is(equal_to(Movement::Left)));
+        assert_that!(Road::new(0, 0, Direction::West, Direction::North).get_movement(), is(equal_to(Movement::Right)));
+        assert_that!(Road::new(0, 0, Direction::West, Direction::South).get_movement(), is(equal_to(Movement::Left)));
+    }
+
+    #[test]
+    fn test_create_edges_with_turn_penalties() {
+        let mut city = City::new(3, 3);
+
+        city.roads = vec![
+            Road::new(1, 0, Direction::North, Direction::East),
+            Road::new(2, 0, Direction::East, Direction::South),
+            Road::new(2, 1, Direction::South, Direction::South),
+            Road::new(2, 1, Direction::South, Direction::West),
+            Road::new(1, 1, Direction::West, Direction::North)
+        ];
+        city.turn_penalties = TurnPenalties::new(1, 2, 3);
+        city.cell_penalties.insert((2, 0, Movement::Right), 5);
+        city.banned_turns.push(Road::new(2, 1, Direction::South, Direction::West));
+
+        let actual = city.create_edges();
+        let expected = [
+            Edge::new(4, 9, 4),
+            Edge::new(9, 22, 6),
+            Edge::new(22, 34, 2),
+            Edge::new(19, 4, 4)
+        ];
+
+        assert_that!(&actual.iter().collect(), contains(expected.iter().collect()).exactly());
+    }
+
     #[test]
     fn test_with_all_roads() {
         let city = City::_with_all_roads(1, 1);
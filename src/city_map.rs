@@ -1,4 +1,4 @@
-use city::{City, Road};
+use city::{City, Road, Movement};
 use {Cell, Direction, DIRECTIONS, get_opposite};
 
 pub fn create_city(text: &str) -> City {
@@ -63,6 +63,12 @@ fn parse_symbol(x: usize, y: usize, text: &str) -> Vec<Transaction> {
             ('S', direction) => vec![parse_source(x, y, direction, text[2..].parse::<usize>().unwrap())],
             ('D', direction) => vec![parse_destination(x, y, direction, text[2..].parse::<usize>().unwrap())],
             ('T', direction) => vec![parse_traffic_light(x, y, direction, text[2..].parse::<usize>().unwrap())],
+            ('A', _) => vec![Transaction::SetTurnPenalty(x, y, Movement::Straight, text[1..].parse::<u32>().unwrap())],
+            ('L', _) => vec![Transaction::SetTurnPenalty(x, y, Movement::Left, text[1..].parse::<u32>().unwrap())],
+            ('R', _) => vec![Transaction::SetTurnPenalty(x, y, Movement::Right, text[1..].parse::<u32>().unwrap())],
+            ('X', entry) if chars.len() == 3 => parse_banned_turn(x, y, entry, chars[2]),
+            ('G', direction) => vec![parse_conflict_group(x, y, direction, text[2..].parse::<usize>().unwrap())],
+            ('C', _) => vec![Transaction::SetCapacity(x, y, text[1..].parse::<usize>().unwrap())],
             (_, _) => panic!("Unknown symbol {}", text),
         },
     }
@@ -91,6 +97,15 @@ fn parse_road(x: usize, y: usize, entry_symbol: char, exit_symbol: char) -> Vec<
     out
 }
 
+fn parse_banned_turn(x: usize, y: usize, entry_symbol: char, exit_symbol: char) -> Vec<Transaction> {
+    parse_road(x, y, entry_symbol, exit_symbol).into_iter().map(|t| {
+        match t {
+            Transaction::AddRoad(road) => Transaction::BanTurn(road),
+            t => t,
+        }
+    }).collect()
+}
+
 fn parse_source(x: usize, y: usize, direction: char, group: usize) -> Transaction {
     let direction = get_direction(direction);
     Transaction::AddSource(group, Cell::new(x, y, direction))
@@ -106,12 +121,21 @@ fn parse_traffic_light(x: usize, y: usize, direction: char, group: usize) -> Tra
     Transaction::AddTrafficLight(group, Cell::new(x, y, direction))
 }
 
+fn parse_conflict_group(x: usize, y: usize, direction: char, group: usize) -> Transaction {
+    let direction = get_direction(direction);
+    Transaction::SetConflictGroup(group, Cell::new(x, y, direction))
+}
+
 #[derive(PartialEq)]
 enum Transaction {
     AddRoad(Road),
     AddSource(usize, Cell),
     AddDestination(usize, Cell),
     AddTrafficLight(usize, Cell),
+    SetTurnPenalty(usize, usize, Movement, u32),
+    BanTurn(Road),
+    SetCapacity(usize, usize, usize),
+    SetConflictGroup(usize, Cell),
 }
 
 fn apply(transaction: Transaction, mut city: City) -> City {
@@ -129,6 +153,17 @@ fn apply(transaction: Transaction, mut city: City) -> City {
             let index = city.get_index(&cell);
             city.lights[group].push(index);
         },
+        Transaction::SetTurnPenalty(x, y, movement, penalty) => {
+            city.cell_penalties.insert((x, y, movement), penalty);
+        },
+        Transaction::BanTurn(road) => city.banned_turns.push(road),
+        Transaction::SetCapacity(x, y, capacity) => {
+            city.capacities.insert((x, y), capacity);
+        },
+        Transaction::SetConflictGroup(group, cell) => {
+            let index = city.get_index(&cell);
+            city.conflict_groups.insert(index, group);
+        },
     }
     city
 }
@@ -148,6 +183,7 @@ mod tests {
 
     use Direction;
     use city_map::*;
+    use occupancy::{Granularity, Occupancy};
 
     #[test]
     fn test_get_direction() {
@@ -271,6 +307,75 @@ mod tests {
         assert!(transactions == vec![Transaction::AddTrafficLight(7, Cell{ x: 1, y: 3, d: Direction::South })]);
     }
 
+    #[test]
+    fn test_parse_turn_penalties() {
+        assert!(parse_symbol(1, 3, "A2") == vec![Transaction::SetTurnPenalty(1, 3, Movement::Straight, 2)]);
+        assert!(parse_symbol(1, 3, "L10") == vec![Transaction::SetTurnPenalty(1, 3, Movement::Left, 10)]);
+        assert!(parse_symbol(1, 3, "R0") == vec![Transaction::SetTurnPenalty(1, 3, Movement::Right, 0)]);
+    }
+
+    #[test]
+    fn test_parse_banned_turn() {
+        let transactions = parse_symbol(1, 3, "X>v");
+        assert!(transactions == vec![Transaction::BanTurn(Road::new(1, 3, Direction::East, Direction::South))]);
+        let transactions = parse_symbol(1, 3, "X*v");
+        assert!(transactions.len() == 3);
+        assert!(transactions.contains(&Transaction::BanTurn(Road::new(1, 3, Direction::West, Direction::South))));
+    }
+
+    #[test]
+    #[should_panic(expected = "Unknown symbol X>")]
+    fn test_parse_short_banned_turn() {
+        parse_symbol(1, 3, "X>");
+    }
+
+    #[test]
+    fn test_create_city_with_turn_restrictions() {
+        let city = create_city("** R4 X^<,");
+        assert!(city.roads.len() == 12);
+        assert!(city.cell_penalties.get(&(0, 0, Movement::Right)) == Some(&4));
+        assert!(city.banned_turns == vec![Road::new(0, 0, Direction::North, Direction::West)]);
+    }
+
+    #[test]
+    fn test_create_city_with_capacity() {
+        let city = create_city("** C3,**");
+        assert!(parse_symbol(1, 3, "C2") == vec![Transaction::SetCapacity(1, 3, 2)]);
+        assert!(city.get_capacity(2) == 3);
+        assert!(city.get_capacity(5) == 1);
+    }
+
+    #[test]
+    fn test_create_city_with_conflict_groups() {
+        assert!(parse_symbol(1, 3, "G^2") == vec![Transaction::SetConflictGroup(2, Cell::new(1, 3, Direction::North))]);
+        // North and south share a group in the first cell, east and west are left to the rest of the cell
+        let city = create_city("** G^0 Gv0,** G>1");
+        let groups = city.get_conflict_groups();
+        assert!(groups[0] == vec![0, 2]);
+        assert!(groups[2] == vec![0, 2]);
+        assert!(groups[1] == vec![1, 3]);
+        assert!(groups[5] == vec![5]);
+        assert!(groups[4] == vec![4, 6, 7]);
+    }
+
+    #[test]
+    fn test_conflict_group_numbers_are_per_cell() {
+        let city = create_city("** G^0 Gv0 G>1 G<1,** G^0 Gv0 G>1 G<1");
+        let groups = city.get_conflict_groups();
+        assert!(groups[0] == vec![0, 2]);
+        assert!(groups[4] == vec![4, 6]);
+        assert!(groups[5] == vec![5, 7]);
+        // A vehicle going north in one cell locks nothing in the other
+        let granularity = Granularity::Groups(groups);
+        for &(locked, ref other) in [(0, 4..8), (4, 0..4)].iter() {
+            let mut occupancy = Occupancy::new(city.get_num_nodes());
+            for node in granularity.get_nodes(locked) {
+                occupancy.lock(node);
+            }
+            assert!(other.clone().all(|n| occupancy.is_unlocked(n)));
+        }
+    }
+
     #[test]
     fn test_parse_cell() {
         let transactions = parse_cell(1, 3, "Sv6 S>3");
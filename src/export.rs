@@ -0,0 +1,62 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use city::City;
+use network::Network;
+use network::export::Attributes;
+
+pub fn get_node_attributes(city: &City, node: usize) -> Attributes {
+    let cell = city.get_cell(node);
+    vec![("x", cell.x.to_string()), ("y", cell.y.to_string()), ("direction", format!("{:?}", cell.d))]
+}
+
+// The format is chosen from the file extension: .dot, .graphml or .csv (edge list). The file is left alone if the
+// extension is not one of these.
+pub fn export_network(city: &City, network: &Network<u32>, path: &Path) -> io::Result<()> {
+    let extension = path.extension().and_then(|e| e.to_str());
+    if !extension.is_some_and(|e| ["dot", "graphml", "csv"].contains(&e)) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "Expected a .dot, .graphml or .csv file"));
+    }
+    let mut out = BufWriter::new(File::create(path)?);
+    let attributes = |n| get_node_attributes(city, n);
+    match extension {
+        Some("dot") => network.write_dot(&mut out, attributes)?,
+        Some("graphml") => network.write_graphml(&mut out, attributes)?,
+        _ => network.write_edges_csv(&mut out)?,
+    }
+    out.flush()
+}
+
+pub fn export_costs(city: &City, network: &Network<u32>, costs: &[Vec<Option<u32>>], path: &Path) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    network.write_costs_csv(&mut out, costs, |n| get_node_attributes(city, n))?;
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::{env, fs};
+    use network::Network;
+    use city_map::create_city;
+    use export::{export_network, get_node_attributes};
+
+    #[test]
+    fn node_attributes() {
+        let city = create_city(",\n,");
+        let attributes = get_node_attributes(&city, 14);
+        assert!(attributes == vec![("x", "1".to_string()), ("y", "1".to_string()), ("direction", "South".to_string())]);
+    }
+
+    #[test]
+    fn unsupported_extension_leaves_file_alone() {
+        let city = create_city(",\n,");
+        let network = Network::new(city.get_num_nodes(), &city.create_edges());
+        let path = env::temp_dir().join("hanger_lane_export_test.txt");
+        fs::write(&path, "keep").unwrap();
+        assert!(export_network(&city, &network, &path).is_err());
+        assert!(fs::read_to_string(&path).unwrap() == "keep");
+        fs::remove_file(&path).unwrap();
+    }
+
+}
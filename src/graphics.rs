@@ -6,6 +6,7 @@ extern crate opengl_graphics;
 use version::{Version, Local};
 use {Traffic, Cell};
 use city::City;
+use simulation::SimulatorHandle;
 use self::piston::window::WindowSettings;
 use self::piston::event_loop::*;
 use self::piston::input::*;
@@ -89,6 +90,8 @@ pub struct Graphics {
     window: Window,
     graphics: GlGraphics,
     grid_size: f64,
+    overlay: Vec<(Cell, [f32; 4])>,
+    controls: Option<SimulatorHandle>,
 }
 
 impl Graphics{
@@ -105,10 +108,22 @@ impl Graphics{
             traffic: Local::new(traffic),
             window: Graphics::create_window(title, width, height, opengl),
             graphics: Graphics::create_graphics(opengl),
-            grid_size
+            grid_size,
+            overlay: vec![],
+            controls: None,
         }
     }
 
+    pub fn set_overlay(&mut self, overlay: Vec<(Cell, [f32; 4])>) {
+        self.overlay = overlay;
+    }
+
+    // Space pauses and resumes, the right arrow runs one step while paused, and the up and down arrows speed up and
+    // slow down the simulation
+    pub fn set_controls(&mut self, controls: SimulatorHandle) {
+        self.controls = Some(controls);
+    }
+
     pub fn create_window(title: &str, width: u32, height: u32, opengl: OpenGL) -> Window {
         WindowSettings::new(title, [width, height])
             .opengl(opengl)
@@ -135,19 +150,37 @@ impl Graphics{
                 self.city.update();
                 self.traffic.update();
             }
+
+            if let Some(Button::Keyboard(key)) = e.press_args() {
+                self.control(key);
+            }
         }
 
     }
 
+    fn control(&self, key: Key) {
+        if let Some(ref controls) = self.controls {
+            match key {
+                Key::Space if controls.is_paused() => controls.resume(),
+                Key::Space => controls.pause(),
+                Key::Right => controls.step(),
+                Key::Up => controls.set_step_ms(controls.get_step_ms() / 2),
+                Key::Down => controls.set_step_ms((controls.get_step_ms() * 2).max(1)),
+                _ => {},
+            }
+        }
+    }
+
     fn render(&mut self, args: &RenderArgs) {
         use graphics::graphics::clear;
         let grid_size = self.grid_size;
+        let overlay = &self.overlay;
 
         if let Some(ref traffic) = self.traffic.local {
             if let Some(ref city) = self.city.local {
                 self.graphics.draw(args.viewport(), |c, gl| {
                     clear(NOT_ROAD_COLOUR, gl);
-                    render_traffic(city, traffic, gl, &c, grid_size);
+                    render_traffic(city, traffic, overlay, gl, &c, grid_size);
                 })
             }
         }
@@ -157,6 +190,7 @@ impl Graphics{
 
 fn render_traffic(city: &City,
                   traffic: &Traffic,
+                  overlay: &[(Cell, [f32; 4])],
                   graphics: &mut GlGraphics,
                   context: &Context,
                   grid_size: f64) {
@@ -189,6 +223,15 @@ fn render_traffic(city: &City,
             context);
     }
 
+    for &(ref cell, colour) in overlay.iter() {
+        draw_cell(cell,
+            colour,
+            1.0,
+            grid_size,
+            graphics,
+            context);
+    }
+
     for vehicle in traffic.vehicles.iter() {
         draw_cell(&city.get_cell(vehicle.location),
             COLOURS[vehicle.destination_index % 64],
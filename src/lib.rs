@@ -1,5 +1,6 @@
 extern crate rand;
 extern crate network;
+extern crate clap;
 #[cfg(test)] #[macro_use] extern crate hamcrest;
 
 pub mod city;
@@ -7,7 +8,15 @@ pub mod city_map;
 pub mod version;
 pub mod simulation;
 pub mod occupancy;
+pub mod lock_counts;
+pub mod reservations;
+pub mod statistics;
 pub mod graphics;
+pub mod export;
+pub mod overlay;
+pub mod report;
+pub mod routes;
+pub mod setup;
 pub mod steps;
 
 const DIRECTIONS: [Direction; 4] = [Direction::North, Direction::East, Direction::South, Direction::West];
@@ -45,15 +54,81 @@ impl Cell {
 
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Vehicle {
+    pub id: usize,
     pub location: usize,
     pub destination: Vec<usize>,
     pub destination_index: usize,
+    // The node and source group the vehicle spawned at, and the step it spawned on
+    pub origin: usize,
+    pub origin_group: usize,
+    pub spawn_step: usize,
+    // How many steps the vehicle has moved and how many it has waited where it was
+    pub moves: usize,
+    pub waited: usize,
+}
+
+impl Vehicle {
+
+    pub fn new(id: usize, location: usize, destination: Vec<usize>, destination_index: usize) -> Vehicle {
+        Vehicle{ id, location, destination, destination_index, origin: location, origin_group: 0, spawn_step: 0, moves: 0, waited: 0 }
+    }
+
 }
 
-#[derive(Clone, Debug)]
+// A finished journey, kept once a vehicle is removed, either on reaching its destination or by being despawned
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trip {
+    pub vehicle: usize,
+    pub origin: usize,
+    pub origin_group: usize,
+    // Where the vehicle was when the trip ended, which is only in its destination if it arrived
+    pub end: usize,
+    pub destination_index: usize,
+    pub spawn_step: usize,
+    pub end_step: usize,
+    pub moves: usize,
+    pub waited: usize,
+    pub arrived: bool,
+}
+
+impl Trip {
+
+    pub fn new(vehicle: &Vehicle, end_step: usize, arrived: bool) -> Trip {
+        Trip{
+            vehicle: vehicle.id,
+            origin: vehicle.origin,
+            origin_group: vehicle.origin_group,
+            end: vehicle.location,
+            destination_index: vehicle.destination_index,
+            spawn_step: vehicle.spawn_step,
+            end_step,
+            moves: vehicle.moves,
+            waited: vehicle.waited,
+            arrived,
+        }
+    }
+
+    pub fn get_travel_time(&self) -> usize {
+        self.end_step - self.spawn_step
+    }
+
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Traffic {
-    pub id: usize,
+    // The step which produced this traffic, so that consumers can tell when they have missed one
+    pub step: usize,
     pub vehicles: Vec<Vehicle>,
+    // Trips which ended on this step
+    pub completed: Vec<Trip>,
+}
+
+impl Traffic {
+
+    pub fn new(vehicles: Vec<Vehicle>) -> Traffic {
+        Traffic{ step: 0, vehicles, completed: vec![] }
+    }
+
 }
@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::mem::size_of;
+
+// How Occupancy stores the number of locks on each node. Callers never remove more locks than a node has.
+pub trait LockCounts {
+    fn get(&self, index: usize) -> usize;
+    fn add(&mut self, index: usize);
+    fn remove(&mut self, index: usize);
+    fn clear(&mut self, index: usize);
+    fn memory_usage(&self) -> usize;
+    fn box_clone(&self) -> Box<LockCounts>;
+}
+
+// A counter for every node
+#[derive(Clone)]
+pub struct Counters {
+    counts: Vec<usize>,
+}
+
+impl Counters {
+    pub fn new(node_count: usize) -> Counters {
+        Counters{ counts: vec![0; node_count] }
+    }
+}
+
+impl LockCounts for Counters {
+
+    fn get(&self, index: usize) -> usize {
+        self.counts[index]
+    }
+
+    fn add(&mut self, index: usize) {
+        self.counts[index] += 1;
+    }
+
+    fn remove(&mut self, index: usize) {
+        self.counts[index] -= 1;
+    }
+
+    fn clear(&mut self, index: usize) {
+        self.counts[index] = 0;
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.counts.len() * size_of::<usize>()
+    }
+
+    fn box_clone(&self) -> Box<LockCounts> {
+        Box::new(self.clone())
+    }
+
+}
+
+// A bit for every node, set while it has any locks, with the locks beyond the first kept in a map. Most nodes have
+// at most one lock, so this takes a fraction of the memory of Counters.
+#[derive(Clone)]
+pub struct Bitset {
+    bits: Vec<u64>,
+    overflow: HashMap<usize, usize>,
+}
+
+impl Bitset {
+    pub fn new(node_count: usize) -> Bitset {
+        Bitset{ bits: vec![0; (node_count + 63) / 64], overflow: HashMap::new() }
+    }
+
+    fn is_set(&self, index: usize) -> bool {
+        self.bits[index / 64] & (1 << (index % 64)) != 0
+    }
+}
+
+impl LockCounts for Bitset {
+
+    fn get(&self, index: usize) -> usize {
+        if self.is_set(index) {
+            1 + self.overflow.get(&index).cloned().unwrap_or(0)
+        } else {
+            0
+        }
+    }
+
+    fn add(&mut self, index: usize) {
+        if self.is_set(index) {
+            *self.overflow.entry(index).or_insert(0) += 1;
+        } else {
+            self.bits[index / 64] |= 1 << (index % 64);
+        }
+    }
+
+    fn remove(&mut self, index: usize) {
+        match self.overflow.get(&index).cloned() {
+            Some(1) => { self.overflow.remove(&index); },
+            Some(extra) => { self.overflow.insert(index, extra - 1); },
+            None => self.bits[index / 64] &= !(1 << (index % 64)),
+        }
+    }
+
+    fn clear(&mut self, index: usize) {
+        self.overflow.remove(&index);
+        self.bits[index / 64] &= !(1 << (index % 64));
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.bits.len() * size_of::<u64>() + self.overflow.capacity() * 2 * size_of::<usize>()
+    }
+
+    fn box_clone(&self) -> Box<LockCounts> {
+        Box::new(self.clone())
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use rand::Rng;
+    use lock_counts::{Bitset, Counters, LockCounts};
+    use simulation::create_rng;
+
+    fn check(counts: &mut LockCounts) {
+        counts.add(70);
+        counts.add(70);
+        counts.add(70);
+        counts.add(3);
+        assert!(counts.get(70) == 3);
+        assert!(counts.get(3) == 1);
+        assert!(counts.get(69) == 0);
+        counts.remove(70);
+        assert!(counts.get(70) == 2);
+        counts.remove(70);
+        counts.remove(70);
+        assert!(counts.get(70) == 0);
+        counts.add(70);
+        counts.add(70);
+        counts.clear(70);
+        assert!(counts.get(70) == 0);
+        assert!(counts.get(3) == 1);
+    }
+
+    #[test]
+    fn counters() {
+        check(&mut Counters::new(100));
+    }
+
+    #[test]
+    fn bitset() {
+        check(&mut Bitset::new(100));
+    }
+
+    #[test]
+    fn bitset_matches_counters() {
+        let mut counters = Counters::new(200);
+        let mut bitset = Bitset::new(200);
+        let mut rng = create_rng(4321);
+        for _ in 0..5000 {
+            let index = rng.gen_range(0, 200);
+            if rng.gen() || counters.get(index) == 0 {
+                counters.add(index);
+                bitset.add(index);
+            } else {
+                counters.remove(index);
+                bitset.remove(index);
+            }
+        }
+        assert!((0..200).all(|i| counters.get(i) == bitset.get(i)));
+    }
+
+}
@@ -1,32 +1,201 @@
-#[derive(Clone)]
+use std::fmt;
+use std::mem::size_of;
+use lock_counts::{Counters, LockCounts};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Holder {
+    Anonymous,
+    Vehicle(usize),
+    TrafficLight(usize),
+    Incident(usize),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum LockError {
+    NoLocks{ node: usize, holder: Holder },
+    NotHeld{ node: usize, holder: Holder, holders: Vec<Holder> },
+}
+
+impl fmt::Display for LockError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LockError::NoLocks{ node, holder } =>
+                write!(f, "No locks left to remove: {:?} tried to unlock node {}", holder, node),
+            LockError::NotHeld{ node, holder, ref holders } =>
+                write!(f, "{:?} tried to unlock node {} which is held by {:?}", holder, node, holders),
+        }
+    }
+}
+
+// Counts the locks on each node. With ownership tracking, each lock also records its holder, so that a mismatched
+// unlock can be traced back to whoever holds the node. A node stays unlocked until its locks reach its capacity.
+// Capacities are only stored once a node is given a capacity other than 1.
 pub struct Occupancy {
-    occupancy: Vec<usize>,
+    node_count: usize,
+    occupancy: Box<LockCounts>,
+    capacity: Option<Vec<usize>>,
+    holders: Option<Vec<Vec<Holder>>>,
+}
+
+impl Clone for Occupancy {
+    fn clone(&self) -> Occupancy {
+        Occupancy{ node_count: self.node_count, occupancy: self.occupancy.box_clone(), capacity: self.capacity.clone(), holders: self.holders.clone() }
+    }
 }
 
 impl Occupancy {
 
     pub fn new(node_count: usize) -> Occupancy {
-        let occupancy = vec![0; node_count];
-        Occupancy{ occupancy }
+        Occupancy::with_lock_counts(node_count, Box::new(Counters::new(node_count)), false)
+    }
+
+    pub fn with_ownership(node_count: usize) -> Occupancy {
+        Occupancy::with_lock_counts(node_count, Box::new(Counters::new(node_count)), true)
+    }
+
+    pub fn with_lock_counts(node_count: usize, occupancy: Box<LockCounts>, track_ownership: bool) -> Occupancy {
+        let holders = if track_ownership { Some(vec![vec![]; node_count]) } else { None };
+        Occupancy{ node_count, occupancy, capacity: None, holders }
+    }
+
+    pub fn is_tracking_ownership(&self) -> bool {
+        self.holders.is_some()
     }
     
+    // Capacities should be set before any locks are taken, as fully locked nodes are unlocked by their capacity
+    pub fn set_capacity(&mut self, index: usize, capacity: usize) {
+        if self.capacity.is_none() && capacity != 1 {
+            self.capacity = Some(vec![1; self.get_node_count()]);
+        }
+        if let Some(ref mut capacities) = self.capacity {
+            capacities[index] = capacity;
+        }
+    }
+
+    pub fn get_capacity(&self, index: usize) -> usize {
+        match self.capacity {
+            Some(ref capacities) => capacities[index],
+            None => 1,
+        }
+    }
+
     pub fn is_unlocked(&self, index: usize) -> bool {
-        self.occupancy[index] == 0
+        self.occupancy.get(index) < self.get_capacity(index)
+    }
+
+    pub fn get_remaining(&self, index: usize) -> usize {
+        self.get_capacity(index).saturating_sub(self.occupancy.get(index))
+    }
+
+    pub fn get_node_count(&self) -> usize {
+        self.node_count
+    }
+
+    pub fn memory_usage(&self) -> usize {
+        self.occupancy.memory_usage() + self.capacity.as_ref().map_or(0, |c| c.len() * size_of::<usize>())
     }
 
     pub fn remove_all_locks(&mut self, index: usize) {
-        self.occupancy[index] = 0;
+        self.occupancy.clear(index);
+        if let Some(ref mut holders) = self.holders {
+            holders[index].clear();
+        }
     }
 
     pub fn unlock(&mut self, index: usize) {
-        if self.occupancy[index] == 0 {
-            panic!("No locks left to remove");
+        if let Err(e) = self.unlock_as(index, Holder::Anonymous) {
+            panic!("{}", e);
         }
-        self.occupancy[index] -= 1;
     }
 
     pub fn lock(&mut self, index: usize) {
-        self.occupancy[index] += 1;
+        self.lock_as(index, Holder::Anonymous);
+    }
+
+    pub fn lock_as(&mut self, index: usize, holder: Holder) {
+        self.occupancy.add(index);
+        if let Some(ref mut holders) = self.holders {
+            holders[index].push(holder);
+        }
+    }
+
+    // Without ownership tracking any holder can remove any lock
+    pub fn unlock_as(&mut self, index: usize, holder: Holder) -> Result<(), LockError> {
+        if self.occupancy.get(index) == 0 {
+            return Err(LockError::NoLocks{ node: index, holder });
+        }
+        if let Some(ref mut holders) = self.holders {
+            match holders[index].iter().position(|h| *h == holder) {
+                Some(position) => { holders[index].remove(position); },
+                None => return Err(LockError::NotHeld{ node: index, holder, holders: holders[index].clone() }),
+            }
+        }
+        self.occupancy.remove(index);
+        Ok(())
+    }
+
+    // Takes every place on the node, so that it stays locked whoever else holds it
+    pub fn lock_fully_as(&mut self, index: usize, holder: Holder) {
+        for _ in 0..self.get_capacity(index) {
+            self.lock_as(index, holder);
+        }
+    }
+
+    pub fn unlock_fully_as(&mut self, index: usize, holder: Holder) -> Result<(), LockError> {
+        for _ in 0..self.get_capacity(index) {
+            self.unlock_as(index, holder)?;
+        }
+        Ok(())
+    }
+
+    pub fn get_holders(&self, index: usize) -> &[Holder] {
+        match self.holders {
+            Some(ref holders) => &holders[index],
+            None => &[],
+        }
+    }
+
+    pub fn get_locks_held_by(&self, holder: Holder) -> Vec<usize> {
+        match self.holders {
+            Some(ref holders) => (0..holders.len())
+                .flat_map(|n| holders[n].iter().filter(|h| **h == holder).map(move |_| n))
+                .collect(),
+            None => vec![],
+        }
+    }
+
+}
+
+// Which nodes a vehicle locks along with the node it is on. Block(4) locks the four directions of a cell together,
+// Node locks each direction on its own, and Groups gives the members of the group of every node. Groups must not
+// overlap, so that nodes locked together always have the same key.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Granularity {
+    Block(usize),
+    Node,
+    Groups(Vec<Vec<usize>>),
+}
+
+impl Granularity {
+
+    pub fn get_nodes(&self, node: usize) -> Vec<usize> {
+        match self {
+            Granularity::Block(size) => {
+                let start = size * (node / size);
+                (start..start + size).collect()
+            },
+            Granularity::Node => vec![node],
+            Granularity::Groups(groups) => groups[node].clone(),
+        }
+    }
+
+    // Nodes which are locked together share a key
+    pub fn get_key(&self, node: usize) -> usize {
+        match self {
+            Granularity::Block(size) => node / size,
+            Granularity::Node => node,
+            Granularity::Groups(groups) => *groups[node].iter().min().unwrap(),
+        }
     }
 
 }
@@ -34,7 +203,8 @@ impl Occupancy {
 #[cfg(test)]
 mod tests {
 
-    use occupancy::Occupancy;
+    use lock_counts::Bitset;
+    use occupancy::{Granularity, Holder, LockError, Occupancy};
 
     #[test]
     fn remove_all_locks() {
@@ -81,5 +251,103 @@ mod tests {
         occupancy.unlock(7);
     }
 
-}
+    #[test]
+    fn lock_as_records_holder() {
+        let mut occupancy = Occupancy::with_ownership(10);
+        occupancy.lock_as(7, Holder::Vehicle(3));
+        occupancy.lock_as(7, Holder::TrafficLight(1));
+        occupancy.lock_as(8, Holder::Vehicle(3));
+        assert!(!occupancy.is_unlocked(7));
+        assert!(occupancy.get_holders(7) == [Holder::Vehicle(3), Holder::TrafficLight(1)]);
+        assert!(occupancy.get_locks_held_by(Holder::Vehicle(3)) == vec![7, 8]);
+        assert!(occupancy.unlock_as(7, Holder::Vehicle(3)) == Ok(()));
+        assert!(occupancy.get_holders(7) == [Holder::TrafficLight(1)]);
+        assert!(occupancy.get_locks_held_by(Holder::Vehicle(3)) == vec![8]);
+    }
+
+    #[test]
+    fn unlock_as_wrong_holder() {
+        let mut occupancy = Occupancy::with_ownership(10);
+        occupancy.lock_as(7, Holder::Vehicle(3));
+        let error = occupancy.unlock_as(7, Holder::Vehicle(4));
+        assert!(error == Err(LockError::NotHeld{ node: 7, holder: Holder::Vehicle(4), holders: vec![Holder::Vehicle(3)] }));
+        assert!(format!("{}", error.unwrap_err()) == "Vehicle(4) tried to unlock node 7 which is held by [Vehicle(3)]");
+        assert!(!occupancy.is_unlocked(7));
+    }
+
+    #[test]
+    fn unlock_as_without_locks() {
+        let mut occupancy = Occupancy::with_ownership(10);
+        let error = occupancy.unlock_as(7, Holder::Incident(2));
+        assert!(error == Err(LockError::NoLocks{ node: 7, holder: Holder::Incident(2) }));
+    }
+
+    #[test]
+    fn unlock_as_without_ownership_tracking() {
+        let mut occupancy = Occupancy::new(10);
+        occupancy.lock_as(7, Holder::Vehicle(3));
+        assert!(occupancy.get_holders(7).is_empty());
+        assert!(occupancy.unlock_as(7, Holder::Vehicle(4)) == Ok(()));
+        assert!(occupancy.is_unlocked(7));
+    }
 
+    #[test]
+    fn remove_all_locks_clears_holders() {
+        let mut occupancy = Occupancy::with_ownership(10);
+        occupancy.lock_as(7, Holder::Vehicle(3));
+        occupancy.remove_all_locks(7);
+        assert!(occupancy.is_unlocked(7));
+        assert!(occupancy.get_holders(7).is_empty());
+    }
+
+    #[test]
+    fn capacity() {
+        let mut occupancy = Occupancy::new(10);
+        occupancy.set_capacity(7, 2);
+        occupancy.lock(7);
+        assert!(occupancy.is_unlocked(7));
+        assert!(occupancy.get_remaining(7) == 1);
+        occupancy.lock(7);
+        assert!(!occupancy.is_unlocked(7));
+        assert!(occupancy.get_remaining(7) == 0);
+        occupancy.unlock(7);
+        assert!(occupancy.is_unlocked(7));
+    }
+
+    #[test]
+    fn lock_fully() {
+        let mut occupancy = Occupancy::with_ownership(10);
+        occupancy.set_capacity(7, 3);
+        occupancy.lock_as(7, Holder::Vehicle(1));
+        occupancy.lock_fully_as(7, Holder::TrafficLight(0));
+        assert!(!occupancy.is_unlocked(7));
+        assert!(occupancy.unlock_fully_as(7, Holder::TrafficLight(0)) == Ok(()));
+        assert!(occupancy.get_holders(7) == [Holder::Vehicle(1)]);
+        assert!(occupancy.get_remaining(7) == 2);
+    }
+
+    #[test]
+    fn granularity() {
+        assert!(Granularity::Block(4).get_nodes(6) == vec![4, 5, 6, 7]);
+        assert!(Granularity::Block(4).get_key(6) == 1);
+        assert!(Granularity::Node.get_nodes(6) == vec![6]);
+        let groups = Granularity::Groups(vec![vec![0, 2], vec![1], vec![0, 2]]);
+        assert!(groups.get_nodes(2) == vec![0, 2]);
+        assert!(groups.get_key(2) == groups.get_key(0));
+        assert!(groups.get_key(1) != groups.get_key(0));
+    }
+
+    #[test]
+    fn bitset_lock_counts() {
+        let mut occupancy = Occupancy::with_lock_counts(100, Box::new(Bitset::new(100)), true);
+        occupancy.set_capacity(70, 2);
+        occupancy.lock_as(70, Holder::Vehicle(1));
+        assert!(occupancy.is_unlocked(70));
+        occupancy.lock_fully_as(70, Holder::TrafficLight(0));
+        assert!(occupancy.get_remaining(70) == 0);
+        assert!(occupancy.unlock_fully_as(70, Holder::TrafficLight(0)) == Ok(()));
+        assert!(occupancy.unlock_as(70, Holder::Vehicle(1)) == Ok(()));
+        assert!(occupancy.unlock_as(70, Holder::Vehicle(1)).is_err());
+    }
+
+}
@@ -0,0 +1,94 @@
+use city::City;
+use network::Network;
+use network::betweenness::Demand;
+use network::flow::MinCut;
+use Cell;
+
+const CUT_COLOUR: [f32; 4] = [1.0, 0.0, 1.0, 0.8];
+
+// Expected number of vehicles passing through each cell, assuming each source group spawns at the same rate and
+// picks a destination group uniformly, as SpawnVehicles does
+pub fn cell_betweenness(city: &City, network: &Network<u32>) -> Vec<f64> {
+    let mut demands = vec![];
+    for sources in city.sources.iter().filter(|s| !s.is_empty()) {
+        for destinations in city.destinations.iter() {
+            demands.push(Demand::new(sources.clone(), destinations.clone(), 1.0 / city.destinations.len() as f64));
+        }
+    }
+    let betweenness = network.betweenness(&demands);
+    let mut out = vec![0.0; city.width * city.height];
+    for (node, value) in betweenness.nodes.iter().enumerate() {
+        out[get_cell_index(city, node)] += *value;
+    }
+    out
+}
+
+pub fn cell_min_cut(city: &City, network: &Network<u32>, source_group: usize, destination_group: usize) -> MinCut {
+    let groups: Vec<usize> = (0..city.get_num_nodes()).map(|n| get_cell_index(city, n)).collect();
+    network.min_cut(&city.sources[source_group], &city.destinations[destination_group], &groups)
+}
+
+pub fn betweenness_overlay(city: &City, betweenness: &[f64]) -> Vec<(Cell, [f32; 4])> {
+    let max = betweenness.iter().cloned().fold(0.0, f64::max);
+    betweenness.iter().enumerate()
+        .filter(|&(_, value)| *value > 0.0)
+        .map(|(index, value)| {
+            let heat = (value / max) as f32;
+            (get_cell(city, index), [1.0, 1.0 - heat, 0.0, 0.3 + 0.5 * heat])
+        })
+        .collect()
+}
+
+pub fn min_cut_overlay(city: &City, min_cut: &MinCut) -> Vec<(Cell, [f32; 4])> {
+    min_cut.groups.iter()
+        .map(|index| (get_cell(city, *index), CUT_COLOUR))
+        .collect()
+}
+
+pub fn describe_cell(city: &City, index: usize) -> String {
+    let cell = get_cell(city, index);
+    format!("({}, {})", cell.x, cell.y)
+}
+
+fn get_cell_index(city: &City, node: usize) -> usize {
+    let cell = city.get_cell(node);
+    cell.y * city.width + cell.x
+}
+
+fn get_cell(city: &City, index: usize) -> Cell {
+    city.get_cell(index * 4)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use city_map::create_city;
+    use network::Network;
+    use overlay::{cell_betweenness, cell_min_cut};
+
+    #[test]
+    fn betweenness_on_single_road() {
+        let city = create_city("S>0 >>,>>,D>0\n,,");
+        let network = Network::new(city.get_num_nodes(), &city.create_edges());
+        let betweenness = cell_betweenness(&city, &network);
+        assert!(betweenness == vec![1.0, 1.0, 1.0, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn betweenness_split_between_destinations() {
+        let city = create_city("S>0 >> >v,D>0\nDv1,");
+        let network = Network::new(city.get_num_nodes(), &city.create_edges());
+        let betweenness = cell_betweenness(&city, &network);
+        assert!(betweenness == vec![1.0, 0.5, 0.5, 0.0]);
+    }
+
+    #[test]
+    fn min_cut_through_single_cell() {
+        let city = create_city("S>0 >> ^>,>>,>>,D>0\nS^0 ^^,,,");
+        let network = Network::new(city.get_num_nodes(), &city.create_edges());
+        let min_cut = cell_min_cut(&city, &network, 0, 0);
+        assert!(min_cut.flow == 1);
+        assert!(min_cut.groups == vec![0]);
+    }
+
+}
@@ -0,0 +1,105 @@
+use std::fmt::Write;
+use city::City;
+use network::Network;
+
+pub fn create_report(city: &City, network: &Network<u32>) -> String {
+    let mut out = String::new();
+
+    let on_road: Vec<usize> = (0..network.nodes)
+        .filter(|n| !network.get_out(*n).is_empty() || !network.get_in(*n).is_empty())
+        .collect();
+    let components: Vec<Vec<usize>> = network.strongly_connected_components().into_iter()
+        .filter(|c| c.len() > 1)
+        .collect();
+    let largest = components.iter().map(|c| c.len()).max().unwrap_or(0);
+    writeln!(out, "{} of {} nodes are on roads", on_road.len(), network.nodes).unwrap();
+    writeln!(out, "{} strongly connected components, largest has {} nodes", components.len(), largest).unwrap();
+
+    let can_reach: Vec<Vec<bool>> = city.destinations.iter().map(|d| network.can_reach(d)).collect();
+
+    for (group, sources) in city.sources.iter().enumerate() {
+        if sources.is_empty() {
+            writeln!(out, "Source group {} has no sources", group).unwrap();
+            continue;
+        }
+        let reachable = network.reachable_from(sources);
+        let unreachable: Vec<usize> = city.destinations.iter().enumerate()
+            .filter(|&(_, d)| !d.iter().any(|n| reachable[*n]))
+            .map(|(d, _)| d)
+            .collect();
+        writeln!(out, "Source group {} reaches {} nodes and {} of {} destination groups",
+                 group,
+                 reachable.iter().filter(|r| **r).count(),
+                 city.destinations.len() - unreachable.len(),
+                 city.destinations.len()).unwrap();
+        if !unreachable.is_empty() {
+            writeln!(out, "  cannot reach destination groups {:?}", unreachable).unwrap();
+        }
+        for source in sources {
+            let stuck: Vec<usize> = can_reach.iter().enumerate()
+                .filter(|&(_, c)| !c[*source])
+                .map(|(d, _)| d)
+                .collect();
+            if !stuck.is_empty() {
+                writeln!(out, "  vehicles spawned at {} for destination groups {:?} will never move", describe(city, *source), stuck).unwrap();
+            }
+        }
+    }
+
+    for (group, can_reach) in can_reach.iter().enumerate() {
+        writeln!(out, "Destination group {} can be reached from {} nodes", group, can_reach.iter().filter(|c| **c).count()).unwrap();
+    }
+
+    let sources: Vec<usize> = city.sources.iter().flat_map(|s| s.iter().cloned()).collect();
+    let destinations: Vec<usize> = city.destinations.iter().flat_map(|d| d.iter().cloned()).collect();
+    let traps = network.traps(&sources, &destinations);
+    writeln!(out, "{} nodes can be reached from a source but cannot exit", traps.len()).unwrap();
+    for trap in traps {
+        writeln!(out, "  {}", describe(city, trap)).unwrap();
+    }
+
+    out
+}
+
+fn describe(city: &City, node: usize) -> String {
+    let cell = city.get_cell(node);
+    format!("({}, {}) {:?}", cell.x, cell.y, cell.d)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use city_map::create_city;
+    use network::Network;
+    use report::create_report;
+
+    fn get_report(map: &str) -> String {
+        let city = create_city(map);
+        let network = Network::new(city.get_num_nodes(), &city.create_edges());
+        create_report(&city, &network)
+    }
+
+    #[test]
+    fn connected_map() {
+        let report = get_report("S>0 >>,>>,D>0");
+        assert!(report.contains("Source group 0 reaches 3 nodes and 1 of 1 destination groups\n"));
+        assert!(report.contains("Destination group 0 can be reached from 3 nodes\n"));
+        assert!(report.contains("0 nodes can be reached from a source but cannot exit\n"));
+    }
+
+    #[test]
+    fn unreachable_destination() {
+        let report = get_report("S>0 >>,>>,D>0\nD<1,,");
+        assert!(report.contains("Source group 0 reaches 3 nodes and 1 of 2 destination groups\n"));
+        assert!(report.contains("  cannot reach destination groups [1]\n"));
+        assert!(report.contains("  vehicles spawned at (0, 0) East for destination groups [1] will never move\n"));
+    }
+
+    #[test]
+    fn trap() {
+        let report = get_report("S>0 >> >v,>>,D>0\n,,");
+        assert!(report.contains("1 nodes can be reached from a source but cannot exit\n"));
+        assert!(report.contains("  (0, 1) South\n"));
+    }
+
+}
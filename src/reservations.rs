@@ -0,0 +1,105 @@
+use std::collections::{HashMap, VecDeque};
+
+// Bookings of nodes by vehicles for each of the coming steps. Slot 0 is the current step, slot 1 the next and so on
+// up to the horizon. Each node can only be booked by one vehicle in each slot.
+pub struct Reservations {
+    slots: VecDeque<HashMap<usize, usize>>,
+}
+
+impl Reservations {
+
+    pub fn new(horizon: usize) -> Reservations {
+        Reservations{ slots: (0..horizon + 1).map(|_| HashMap::new()).collect() }
+    }
+
+    pub fn get_horizon(&self) -> usize {
+        self.slots.len() - 1
+    }
+
+    pub fn get(&self, node: usize, slot: usize) -> Option<usize> {
+        self.slots[slot].get(&node).cloned()
+    }
+
+    pub fn is_free_for(&self, node: usize, slot: usize, vehicle: usize) -> bool {
+        self.get(node, slot).map_or(true, |v| v == vehicle)
+    }
+
+    // Returns false, without booking, if another vehicle holds the slot
+    pub fn book(&mut self, node: usize, slot: usize, vehicle: usize) -> bool {
+        if !self.is_free_for(node, slot, vehicle) {
+            return false;
+        }
+        self.slots[slot].insert(node, vehicle);
+        true
+    }
+
+    // Removes every booking held by the vehicle, returning the (node, slot) of each
+    pub fn cancel(&mut self, vehicle: usize) -> Vec<(usize, usize)> {
+        let mut out = vec![];
+        for (slot, bookings) in self.slots.iter_mut().enumerate() {
+            let nodes: Vec<usize> = bookings.iter().filter(|b| *b.1 == vehicle).map(|b| *b.0).collect();
+            for node in nodes {
+                bookings.remove(&node);
+                out.push((node, slot));
+            }
+        }
+        out.sort();
+        out
+    }
+
+    // The (node, vehicle) of each booking in a slot
+    pub fn get_bookings(&self, slot: usize) -> Vec<(usize, usize)> {
+        let mut out: Vec<(usize, usize)> = self.slots[slot].iter().map(|(n, v)| (*n, *v)).collect();
+        out.sort();
+        out
+    }
+
+    // Moves on to the next step, returning the bookings of the step just finished
+    pub fn advance(&mut self) -> Vec<(usize, usize)> {
+        let finished = self.get_bookings(0);
+        self.slots.pop_front();
+        self.slots.push_back(HashMap::new());
+        finished
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use reservations::Reservations;
+
+    #[test]
+    fn one_vehicle_per_slot() {
+        let mut reservations = Reservations::new(2);
+        assert!(reservations.book(5, 1, 0));
+        assert!(reservations.book(5, 1, 0));
+        assert!(!reservations.book(5, 1, 1));
+        assert!(reservations.book(5, 2, 1));
+        assert!(reservations.get(5, 1) == Some(0));
+        assert!(reservations.is_free_for(5, 0, 1));
+    }
+
+    #[test]
+    fn cancel_removes_every_booking() {
+        let mut reservations = Reservations::new(3);
+        reservations.book(1, 1, 7);
+        reservations.book(2, 2, 7);
+        reservations.book(3, 2, 8);
+        assert!(reservations.cancel(7) == vec![(1, 1), (2, 2)]);
+        assert!(reservations.get_bookings(1).is_empty());
+        assert!(reservations.get_bookings(2) == vec![(3, 8)]);
+    }
+
+    #[test]
+    fn advance_moves_slots_forward() {
+        let mut reservations = Reservations::new(2);
+        reservations.book(1, 0, 7);
+        reservations.book(2, 2, 7);
+        assert!(reservations.advance() == vec![(1, 7)]);
+        assert!(reservations.get(2, 1) == Some(7));
+        assert!(reservations.get_bookings(2).is_empty());
+        assert!(reservations.get_horizon() == 2);
+    }
+
+}
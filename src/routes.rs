@@ -0,0 +1,114 @@
+use network::Network;
+use network::contraction::{ContractionHierarchy, TargetCosts};
+
+// The network and the cost of reaching each group of targets, shared by the steps that follow and re-weight routes.
+// Costs come from a cost field per group, or from a contraction hierarchy queried as they are needed.
+pub struct Routes {
+    pub network: Network<u32>,
+    pub targets: Vec<Vec<usize>>,
+    pub costs: Vec<Vec<Option<u32>>>,
+    pub hierarchy: Option<ContractionHierarchy<u32>>,
+    // Searched once per group of targets whenever the hierarchy is built, so that each query only searches upwards
+    pub target_costs: Vec<TargetCosts<u32>>,
+}
+
+impl Routes {
+
+    pub fn new(network: Network<u32>, targets: Vec<Vec<usize>>, costs: Vec<Vec<Option<u32>>>) -> Routes {
+        Routes{ network, targets, costs, hierarchy: None, target_costs: vec![] }
+    }
+
+    pub fn with_hierarchy(network: Network<u32>, targets: Vec<Vec<usize>>) -> Routes {
+        let hierarchy = network.contraction_hierarchy();
+        let target_costs = targets.iter().map(|t| hierarchy.target_costs(t)).collect();
+        Routes{ network, targets, costs: vec![], hierarchy: Some(hierarchy), target_costs }
+    }
+
+    // The cost from a node to the nearest target in a group
+    pub fn get_cost(&self, target: usize, node: usize) -> Option<u32> {
+        match self.hierarchy {
+            Some(ref hierarchy) => hierarchy.cost_to(node, &self.target_costs[target]),
+            None => self.costs[target][node],
+        }
+    }
+
+    // Sets the cost of every edge (in the order of Network::edges) and repairs the cost fields, or rebuilds the
+    // hierarchy, which can't be repaired. Rebuilding takes seconds on large maps (see contraction_benchmark).
+    // Returns the number of edges that changed.
+    pub fn set_costs(&mut self, costs: &[u32]) -> usize {
+        let changed_edges: Vec<(usize, usize, u32)> = self.network.edges().iter().zip(costs.iter())
+            .filter(|&(edge, cost)| edge.cost != *cost)
+            .map(|(edge, cost)| (edge.from, edge.to, *cost))
+            .collect();
+        if changed_edges.is_empty() {
+            return 0;
+        }
+        let mut changed: Vec<usize> = vec![];
+        for &(from, to, cost) in changed_edges.iter() {
+            self.network.set_cost(from, to, cost);
+            changed.push(from);
+        }
+        changed.dedup();
+        for (costs, targets) in self.costs.iter_mut().zip(self.targets.iter()) {
+            self.network.update_dijkstra(costs, targets, &changed);
+        }
+        if self.hierarchy.is_some() {
+            let hierarchy = self.network.contraction_hierarchy();
+            self.target_costs = self.targets.iter().map(|t| hierarchy.target_costs(t)).collect();
+            self.hierarchy = Some(hierarchy);
+        }
+        changed_edges.len()
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use network::{Edge, Network};
+    use routes::Routes;
+
+    #[test]
+    fn set_costs_repairs_cost_fields() {
+        let edges = Edge::create_grid(4, 4, 1, Edge::<u32>::create_4_neighbour_deltas());
+        let network = Network::new(16, &edges);
+        let targets = vec![vec![15], vec![0, 3]];
+        let costs = targets.iter().map(|t| network.dijkstra(t.clone())).collect();
+        let mut routes = Routes::new(network, targets.clone(), costs);
+
+        let new_costs: Vec<u32> = routes.network.edges().iter().map(|e| if e.to == 5 || e.to == 10 { 10 } else { 1 }).collect();
+        assert!(routes.set_costs(&new_costs) > 0);
+
+        let new_edges: Vec<Edge<u32>> = routes.network.edges().to_vec();
+        let fresh = Network::new(16, &new_edges);
+        for (costs, targets) in routes.costs.iter().zip(targets.iter()) {
+            assert!(*costs == fresh.dijkstra(targets.clone()));
+        }
+        assert!(routes.set_costs(&new_costs) == 0);
+    }
+
+    #[test]
+    fn hierarchy_costs_match_cost_fields() {
+        let edges = Edge::create_grid(4, 4, 1, Edge::<u32>::create_4_neighbour_deltas());
+        let network = Network::new(16, &edges);
+        let targets = vec![vec![15], vec![0, 3]];
+        let costs = targets.iter().map(|t| network.dijkstra(t.clone())).collect();
+        let mut fields = Routes::new(network, targets.clone(), costs);
+        let mut hierarchy = Routes::with_hierarchy(Network::new(16, &edges), targets.clone());
+        assert!(hierarchy.costs.is_empty());
+
+        let new_costs: Vec<u32> = fields.network.edges().iter().map(|e| if e.to == 5 || e.to == 10 { 10 } else { 1 }).collect();
+        for &set in [false, true].iter() {
+            if set {
+                fields.set_costs(&new_costs);
+                hierarchy.set_costs(&new_costs);
+            }
+            for target in 0..targets.len() {
+                for node in 0..16 {
+                    assert!(hierarchy.get_cost(target, node) == fields.get_cost(target, node));
+                }
+            }
+        }
+    }
+
+}
@@ -0,0 +1,376 @@
+use std::cell::RefCell;
+use std::env;
+use std::fs;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::thread;
+use clap::{App, Arg, ArgMatches, Error, ErrorKind};
+use rand;
+use rand::Rng;
+use network::Network;
+use city::{City, TurnPenalties};
+use city_map::create_city;
+use lock_counts::{Bitset, Counters, LockCounts};
+use occupancy::{Granularity, Occupancy};
+use reservations::Reservations;
+use routes::Routes;
+use simulation::{Clock, Simulation, SimulationState, SimulationStep, UpdateVehicles, VehicleUpdate, create_rng};
+use steps::block_occupier::{VehicleFree, VehicleOccupy};
+use steps::congestion::Congestion;
+use steps::gridlock::{GridlockDetector, Resolution};
+use steps::lookahead_driver::LookaheadDriver;
+use steps::reservation_booker::{AdvanceReservations, VehicleBook, VehicleCancel};
+use steps::spawner::{RemoveVehicles, SpawnVehicles};
+use steps::traffic_lights::{Timer, TrafficLights};
+use Traffic;
+
+// Setup shared by the windowed and headless binaries
+
+pub fn add_simulation_args<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+    app.arg(Arg::with_name("file")
+            .help("Map of city to simulate")
+            .required(true)
+            .index(1))
+        .arg(Arg::with_name("turn_penalties")
+             .help("Extra cost of going straight on, turning left and turning right when finding paths, given as <straight>,<left>,<right>")
+             .long("turn_penalties")
+             .default_value("0,0,0"))
+        .arg(Arg::with_name("spawn_frequency")
+             .help("On each step, each source will produce a vehicle with probability 1/spawn_frequency")
+             .long("spawn_frequency")
+             .default_value("8"))
+        .arg(Arg::with_name("lookahead")
+             .help("Vehicles will lookahead by this number of cells (to get around obstacles). Higher values reduce performance.")
+             .long("lookahead")
+             .default_value("3"))
+        .arg(Arg::with_name("occupancy")
+             .help("What a vehicle blocks: its whole cell, only its direction of travel in the cell, or the conflict group its direction is in (set in the map)")
+             .long("occupancy")
+             .possible_values(&["cell", "direction", "groups"])
+             .default_value("cell"))
+        .arg(Arg::with_name("reservation_horizon")
+             .help("Vehicles book the cells along their route for this many steps ahead, and cannot enter cells booked by others (0 to disable)")
+             .long("reservation_horizon")
+             .default_value("0"))
+        .arg(Arg::with_name("step_seconds")
+             .help("Simulated seconds that each step stands for")
+             .long("step_seconds")
+             .default_value("1"))
+        .arg(Arg::with_name("even_cycle_steps")
+             .help("Even traffic light cycles last this many steps")
+             .long("even_cycle_steps")
+             .default_value("8"))
+        .arg(Arg::with_name("odd_cycle_steps")
+             .help("Odd traffic light cycles last this many steps")
+             .long("odd_cycle_steps")
+             .default_value("50"))
+        .arg(Arg::with_name("congestion_interval")
+             .help("Re-weight routes by how congested each cell has been every this many steps (0 to disable)")
+             .long("congestion_interval")
+             .default_value("0"))
+        .arg(Arg::with_name("congestion_penalty")
+             .help("Extra cost of entering a cell which was occupied for every step of the last congestion interval")
+             .long("congestion_penalty")
+             .default_value("10"))
+        .arg(Arg::with_name("track_locks")
+             .help("Record which vehicle or traffic light holds each lock, so that errors name the holder")
+             .long("track_locks"))
+        .arg(Arg::with_name("gridlock")
+             .help("Detect vehicles waiting for each other in a cycle, and either report them, freeze the simulation, despawn the vehicle which has waited longest or move it away from its destination")
+             .long("gridlock")
+             .possible_values(&["report", "freeze", "despawn", "move"])
+             .takes_value(true))
+        .arg(Arg::with_name("lock_counts")
+             .help("How the locks on each node are stored: a counter per node, or a bit per node with a map for the rare nodes with more than one lock (less memory on large maps)")
+             .long("lock_counts")
+             .possible_values(&["counters", "bitset"])
+             .default_value("counters"))
+        .arg(Arg::with_name("seed")
+             .help("Seed for the random numbers used in the simulation, so that a run can be repeated exactly (random by default)")
+             .long("seed")
+             .takes_value(true)
+             .validator(validate_seed))
+        .arg(Arg::with_name("scenario")
+             .help("File of options for the simulation, with an <option> = <value> line for each (e.g. seed = 42, or track_locks = true for a flag). Options given on the command line take precedence.")
+             .long("scenario")
+             .takes_value(true))
+        .arg(Arg::with_name("threads")
+             .help("Number of threads used to calculate routes at startup (defaults to available parallelism)")
+             .long("threads")
+             .takes_value(true))
+        .arg(Arg::with_name("routing")
+             .help("How vehicles find their routes: a cost field per destination calculated at startup, or a contraction hierarchy queried as they drive (quicker to start on large maps, slower to run)")
+             .long("routing")
+             .possible_values(&["fields", "contraction"])
+             .default_value("fields"))
+        .arg(Arg::with_name("cost_cache")
+             .help("File used to cache routes between runs, recalculated if the map has changed")
+             .long("cost_cache")
+             .takes_value(true))
+}
+
+pub fn load_city(args: &ArgMatches) -> City {
+    let mut f = File::open(args.value_of("file").unwrap()).expect("File not found");
+    let mut contents = String::new();
+    f.read_to_string(&mut contents).expect("Failed to read file");
+    let mut city = create_city(&contents);
+    city.turn_penalties = parse_turn_penalties(args.value_of("turn_penalties").unwrap());
+    city
+}
+
+pub fn get_threads(args: &ArgMatches) -> usize {
+    match args.value_of("threads") {
+        Some(threads) => threads.parse().unwrap(),
+        None => thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+    }
+}
+
+fn parse_turn_penalties(text: &str) -> TurnPenalties {
+    let penalties: Vec<u32> = text.split(',').map(|p| p.trim().parse().expect("Expected <straight>,<left>,<right>")).collect();
+    assert!(penalties.len() == 3, "Expected <straight>,<left>,<right>");
+    TurnPenalties::new(penalties[0], penalties[1], penalties[2])
+}
+
+fn get_granularity(city: &City, args: &ArgMatches) -> Granularity {
+    match args.value_of("occupancy").unwrap() {
+        "direction" => Granularity::Node,
+        "groups" => Granularity::Groups(city.get_conflict_groups()),
+        _ => Granularity::Block(4),
+    }
+}
+
+// Parses the command line, adding the options from any --scenario file which weren't given on the command line
+pub fn get_matches(app: App<'static, 'static>) -> ArgMatches<'static> {
+    get_matches_from(app, env::args().collect())
+}
+
+fn get_matches_from(app: App<'static, 'static>, args: Vec<String>) -> ArgMatches<'static> {
+    let matches = app.clone().get_matches_from(args.iter());
+    let path = match matches.value_of("scenario") {
+        Some(path) => path.to_string(),
+        None => return matches,
+    };
+    let scenario = fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| parse_scenario(&text))
+        .unwrap_or_else(|e| Error::with_description(&format!("Invalid scenario {}: {}", path, e), ErrorKind::InvalidValue).exit());
+    let mut args = args;
+    for (option, value) in scenario.into_iter().filter(|(option, _)| matches.occurrences_of(option) == 0) {
+        match value.as_str() {
+            "true" => args.push(format!("--{}", option)),
+            "false" => {},
+            _ => args.push(format!("--{}={}", option, value)),
+        }
+    }
+    app.get_matches_from(args)
+}
+
+// Blank lines and lines starting with # are skipped
+fn parse_scenario(text: &str) -> Result<Vec<(String, String)>, String> {
+    text.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let parts: Vec<&str> = line.splitn(2, '=').map(|p| p.trim()).collect();
+            if parts.len() == 2 && !parts[0].is_empty() {
+                Ok((parts[0].to_string(), parts[1].to_string()))
+            } else {
+                Err(format!("Expected <option> = <value>, not {}", line))
+            }
+        })
+        .collect()
+}
+
+fn validate_seed(seed: String) -> Result<(), String> {
+    seed.parse::<usize>().map(|_| ()).map_err(|_| format!("Expected a whole number, not {}", seed))
+}
+
+// A random seed is chosen unless one is given, and printed so that the run can be repeated
+pub fn get_seed(args: &ArgMatches) -> usize {
+    let seed = match args.value_of("seed") {
+        Some(seed) => seed.parse().unwrap(),
+        None => rand::thread_rng().gen(),
+    };
+    println!("Seed: {}", seed);
+    seed
+}
+
+fn parse_resolution(text: &str) -> Resolution {
+    match text {
+        "freeze" => Resolution::Freeze,
+        "despawn" => Resolution::DespawnLongestWaiting,
+        "move" => Resolution::NonImprovingMove,
+        _ => Resolution::Report,
+    }
+}
+
+pub fn create_occupancy(city: &City, args: &ArgMatches) -> Occupancy {
+    let nodes = city.get_num_nodes();
+    let lock_counts: Box<LockCounts> = match args.value_of("lock_counts").unwrap() {
+        "bitset" => Box::new(Bitset::new(nodes)),
+        _ => Box::new(Counters::new(nodes)),
+    };
+    let mut occupancy = Occupancy::with_lock_counts(nodes, lock_counts, args.is_present("track_locks"));
+    for node in 0..nodes {
+        occupancy.set_capacity(node, city.get_capacity(node));
+    }
+    occupancy
+}
+
+pub fn create_state(occupancy: Occupancy, seed: usize, args: &ArgMatches) -> SimulationState {
+    let traffic = Traffic::new(vec![]);
+    let clock = Clock::new(args.value_of("step_seconds").unwrap().parse().unwrap());
+    SimulationState{ clock, ..SimulationState::new(traffic, occupancy, create_rng(seed)) }
+}
+
+// Every step except for any delay between steps, which is up to the caller
+pub fn create_simulation(city: &Arc<City>,
+                         occupancy: &mut Occupancy,
+                         args: &ArgMatches) -> Simulation {
+    let spawn_frequency = args.value_of("spawn_frequency").unwrap().parse().unwrap();
+    let lookahead = args.value_of("lookahead").unwrap().parse().unwrap();
+    let traffic_light_even_cycle_steps = args.value_of("even_cycle_steps").unwrap().parse().unwrap();
+    let traffic_light_odd_cycle_steps = args.value_of("odd_cycle_steps").unwrap().parse().unwrap();
+    let congestion_interval = args.value_of("congestion_interval").unwrap().parse().unwrap();
+    let congestion_penalty = args.value_of("congestion_penalty").unwrap().parse().unwrap();
+    let reservation_horizon = args.value_of("reservation_horizon").unwrap().parse().unwrap();
+
+    let threads = get_threads(args);
+    let routing = args.value_of("routing").unwrap();
+    assert!(routing != "contraction" || congestion_interval == 0,
+            "--congestion_interval can't be used with --routing contraction, which would rebuild the hierarchy on every re-weight");
+
+    let network = Network::new(city.get_num_nodes(), &city.create_edges());
+    let routes = match routing {
+        "contraction" => Routes::with_hierarchy(network, city.destinations.clone()),
+        _ => {
+            let costs = match args.value_of("cost_cache") {
+                Some(path) => load_or_calculate_costs(&network, &city.destinations, threads, path),
+                None => network.dijkstra_all(city.destinations.clone(), threads),
+            };
+            Routes::new(network, city.destinations.clone(), costs)
+        },
+    };
+    let routes = Rc::new(RefCell::new(routes));
+    let granularity = get_granularity(city, args);
+    let add_vehicles = Box::new(SpawnVehicles::new(Arc::clone(city), granularity.clone(), spawn_frequency));
+    let reservations = Rc::new(RefCell::new(Reservations::new(reservation_horizon)));
+    let vehicle_updates: Vec<Box<VehicleUpdate>> = if reservation_horizon > 0 {
+        vec![
+            Box::new(VehicleCancel::new(granularity.clone(), Rc::clone(&reservations))),
+            Box::new(LookaheadDriver::new(lookahead, Rc::clone(&routes))),
+            Box::new(VehicleBook::new(granularity.clone(), Rc::clone(&reservations), Rc::clone(&routes))),
+        ]
+    } else {
+        vec![
+            Box::new(VehicleFree::with_granularity(granularity.clone())),
+            Box::new(LookaheadDriver::new(lookahead, Rc::clone(&routes))),
+            Box::new(VehicleOccupy::with_granularity(granularity.clone())),
+        ]
+    };
+    let update_vehicles = Box::new(UpdateVehicles{updates: vehicle_updates});
+    let remove_vehicles = Box::new(RemoveVehicles{});
+
+    let mut steps: Vec<Box<SimulationStep>> = vec![];
+    if !city.lights.is_empty() {
+        steps.push(Box::new(TrafficLights::new(city.lights.clone(),
+            RefCell::new(Box::new(CounterTimer::new(vec![traffic_light_even_cycle_steps, traffic_light_odd_cycle_steps]))),
+            occupancy)));
+    }
+    steps.push(add_vehicles);
+    if reservation_horizon > 0 {
+        steps.push(Box::new(AdvanceReservations::new(reservations)));
+    }
+    steps.push(update_vehicles);
+    if let Some(resolution) = args.value_of("gridlock") {
+        steps.push(Box::new(GridlockDetector::new(Arc::clone(city), Rc::clone(&routes), granularity, parse_resolution(resolution))));
+    }
+    if congestion_interval > 0 {
+        steps.push(Box::new(Congestion::new(routes, 4, congestion_interval, congestion_penalty)));
+    }
+    steps.push(remove_vehicles);
+    Simulation{ steps }
+}
+
+fn load_or_calculate_costs(network: &Network<u32>, targets: &[Vec<usize>], threads: usize, path: &str) -> Vec<Vec<Option<u32>>> {
+    if Path::new(path).exists() {
+        match network.load_costs(path, targets) {
+            Ok(Some(costs)) => return costs,
+            Ok(None) => println!("Cost cache {} is out of date, recalculating", path),
+            Err(e) => println!("Could not read cost cache {}: {}", path, e),
+        }
+    }
+    let costs = network.dijkstra_all(targets.to_vec(), threads);
+    if let Err(e) = network.save_costs(path, targets, &costs) {
+        println!("Could not write cost cache {}: {}", path, e);
+    }
+    costs
+}
+
+pub struct CounterTimer {
+    counter: usize,
+    cycle: usize,
+    targets: Vec<usize>,
+}
+
+impl CounterTimer {
+    pub fn new(targets: Vec<usize>) -> CounterTimer {
+        CounterTimer{ counter: 0, cycle: 0, targets }
+    }
+}
+
+impl Timer for CounterTimer {
+    fn ready(&mut self) -> bool {
+        let out = self.counter >= self.targets[self.cycle];
+        self.counter += 1;
+        out
+    }
+
+    fn reset(&mut self) {
+        self.counter = 0;
+        self.cycle = (self.cycle + 1) % self.targets.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::env;
+    use std::fs;
+    use clap::App;
+    use setup::{add_simulation_args, get_matches_from, parse_scenario};
+
+    fn get_app() -> App<'static, 'static> {
+        add_simulation_args(App::new("test"))
+    }
+
+    #[test]
+    fn parses_scenario() {
+        let scenario = parse_scenario("# A repeatable run\nseed = 42\n\n  track_locks=true  \n").unwrap();
+        assert!(scenario == vec![("seed".to_string(), "42".to_string()), ("track_locks".to_string(), "true".to_string())]);
+        assert!(parse_scenario("seed 42").is_err());
+        assert!(parse_scenario("= 42").is_err());
+    }
+
+    #[test]
+    fn command_line_overrides_scenario() {
+        let path = env::temp_dir().join("hanger_lane_scenario_test.txt");
+        fs::write(&path, "seed = 5\nspawn_frequency = 3\ntrack_locks = true\n").unwrap();
+        let args = vec!["test", "map.csv", "--scenario", path.to_str().unwrap(), "--seed", "7"];
+        let matches = get_matches_from(get_app(), args.into_iter().map(|a| a.to_string()).collect());
+        fs::remove_file(&path).unwrap();
+        assert!(matches.value_of("seed") == Some("7"));
+        assert!(matches.value_of("spawn_frequency") == Some("3"));
+        assert!(matches.is_present("track_locks"));
+    }
+
+    #[test]
+    fn rejects_seed_which_is_not_a_number() {
+        assert!(get_app().get_matches_from_safe(vec!["test", "map.csv", "--seed", "abc"]).is_err());
+        assert!(get_app().get_matches_from_safe(vec!["test", "map.csv", "--seed", "12"]).is_ok());
+    }
+
+}
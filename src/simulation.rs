@@ -1,16 +1,98 @@
 extern crate rand;
 extern crate network;
 
-use std::sync::{Arc, RwLock};
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
 use version::{Version, Publisher};
-use super::{Vehicle, Traffic};
-use rand::Rng;
+use super::{Cell, Vehicle, Traffic};
+use rand::{Rng, SeedableRng, StdRng};
 use occupancy::Occupancy;
+use statistics::Statistics;
+
+// Something noteworthy that happened during a step, printed by the simulator once the step is done
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    Gridlock{ step: usize, vehicles: Vec<usize>, cells: Vec<Cell> },
+    Despawned{ step: usize, vehicle: usize, cell: Cell },
+    Moved{ step: usize, vehicle: usize, from: Cell, to: Cell },
+}
+
+fn format_cell(cell: &Cell) -> String {
+    format!("({}, {}, {:?})", cell.x, cell.y, cell.d)
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Event::Gridlock{ step, vehicles, cells } => {
+                let cells: Vec<String> = cells.iter().map(format_cell).collect();
+                write!(f, "Step {}: gridlock between vehicles {:?} at {}", step, vehicles, cells.join(" "))
+            },
+            Event::Despawned{ step, vehicle, cell } =>
+                write!(f, "Step {}: despawned vehicle {} at {}", step, vehicle, format_cell(cell)),
+            Event::Moved{ step, vehicle, from, to } =>
+                write!(f, "Step {}: moved vehicle {} away from its destination, from {} to {}", step, vehicle, format_cell(from), format_cell(to)),
+        }
+    }
+}
+
+// Counts the steps run so far. Each step stands for a fixed length of simulated time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Clock {
+    step: usize,
+    step_seconds: f64,
+}
+
+impl Clock {
+
+    pub fn new(step_seconds: f64) -> Clock {
+        Clock{ step: 0, step_seconds }
+    }
+
+    // The step being run, counting from 0
+    pub fn get_step(&self) -> usize {
+        self.step
+    }
+
+    pub fn get_step_seconds(&self) -> f64 {
+        self.step_seconds
+    }
+
+    // Simulated time at the start of the step being run
+    pub fn get_seconds(&self) -> f64 {
+        self.step as f64 * self.step_seconds
+    }
+
+    pub fn tick(&mut self) {
+        self.step += 1;
+    }
+
+}
 
 pub struct SimulationState {
+    pub clock: Clock,
     pub traffic: Traffic,
     pub occupancy: Occupancy,
     pub rng: Box<Rng>,
+    pub events: Vec<Event>,
+    pub statistics: Statistics,
+    // Set by a step to stop the simulator from running any further steps
+    pub halted: bool,
+}
+
+impl SimulationState {
+
+    pub fn new(traffic: Traffic, occupancy: Occupancy, rng: Box<Rng>) -> SimulationState {
+        SimulationState{ clock: Clock::new(1.0), traffic, occupancy, rng, events: vec![], statistics: Statistics::new(), halted: false }
+    }
+
+}
+
+// Runs are repeatable: the same seed always gives the same traffic
+pub fn create_rng(seed: usize) -> Box<Rng> {
+    let seed: &[usize] = &[seed];
+    Box::new(StdRng::from_seed(seed))
 }
 
 pub trait SimulationStep {
@@ -31,11 +113,17 @@ impl SimulationStep for UpdateVehicles {
         let mut occupancy = state.occupancy;
         let mut rng = state.rng;
         for mut vehicle in traffic.vehicles.iter_mut() {
+            let location = vehicle.location;
             for update in self.updates.iter() {
                 update.update(&mut vehicle, &mut occupancy, &mut rng);
             }
+            if vehicle.location == location {
+                vehicle.waited += 1;
+            } else {
+                vehicle.moves += 1;
+            }
         }
-        SimulationState{ traffic, occupancy, rng}
+        SimulationState{ traffic, occupancy, rng, ..state }
     }
 }
 
@@ -45,55 +133,212 @@ pub struct Simulation {
 
 impl Simulation {
 
-    fn step(&self, state: SimulationState) -> SimulationState {
+    pub fn step(&self, state: SimulationState) -> SimulationState {
 
         let mut state = state;
+        state.traffic.completed.clear();
         for step in self.steps.iter() {
             state = step.step(state);
         }
+        state.traffic.step = state.clock.get_step();
+        state.clock.tick();
         state
     }
 }
 
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Mode {
+    Paused,
+    Running,
+    // Run this many more steps, then pause
+    Steps(usize),
+}
+
+struct Control {
+    mode: Mode,
+    step_ms: u64,
+    shutting_down: bool,
+}
+
+// Drives a Simulator from other threads. The simulator thread blocks while paused, and while waiting out the delay
+// between steps, until something changes.
+#[derive(Clone)]
+pub struct SimulatorHandle {
+    control: Arc<(Mutex<Control>, Condvar)>,
+}
+
+impl SimulatorHandle {
+
+    // Starts paused
+    pub fn new(step_ms: u64) -> SimulatorHandle {
+        let control = Control{ mode: Mode::Paused, step_ms, shutting_down: false };
+        SimulatorHandle{ control: Arc::new((Mutex::new(control), Condvar::new())) }
+    }
+
+    fn update<F: FnOnce(&mut Control)>(&self, f: F) {
+        let (control, changed) = &*self.control;
+        f(&mut control.lock().unwrap());
+        changed.notify_all();
+    }
+
+    pub fn pause(&self) {
+        self.update(|c| c.mode = Mode::Paused);
+    }
+
+    pub fn resume(&self) {
+        self.update(|c| c.mode = Mode::Running);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.control.0.lock().unwrap().mode == Mode::Paused
+    }
+
+    pub fn step(&self) {
+        self.run_n(1);
+    }
+
+    pub fn run_n(&self, steps: usize) {
+        self.update(|c| c.mode = if steps == 0 { Mode::Paused } else { Mode::Steps(steps) });
+    }
+
+    pub fn get_step_ms(&self) -> u64 {
+        self.control.0.lock().unwrap().step_ms
+    }
+
+    pub fn set_step_ms(&self, step_ms: u64) {
+        self.update(|c| c.step_ms = step_ms);
+    }
+
+    pub fn shutdown(&self) {
+        self.update(|c| c.shutting_down = true);
+    }
+
+}
+
 pub struct Simulator {
     simulation: Simulation,
     traffic_publisher: Publisher<Traffic>,
-    running: Arc<RwLock<bool>>,
-    shutting_down: Arc<RwLock<bool>>,
+    handle: SimulatorHandle,
 }
 
 impl Simulator {
 
     pub fn new(simulation: Simulation,
                traffic: &Version<Traffic>,
-               running: Arc<RwLock<bool>>,
-               shutting_down: Arc<RwLock<bool>>) -> Simulator {
+               handle: SimulatorHandle) -> Simulator {
         Simulator{
             simulation,
             traffic_publisher: Publisher::new(traffic),
-            running,
-            shutting_down,
+            handle,
+        }
+    }
+
+    // Blocks until there is a step to run, returning false on shutdown
+    fn wait_for_step(&self, halted: bool) -> bool {
+        let (control, changed) = &*self.handle.control;
+        let control = changed.wait_while(control.lock().unwrap(), |c| {
+            !c.shutting_down && (halted || c.mode == Mode::Paused)
+        });
+        let mut control = control.unwrap();
+        if control.shutting_down {
+            return false;
         }
+        if let Mode::Steps(steps) = control.mode {
+            control.mode = if steps > 1 { Mode::Steps(steps - 1) } else { Mode::Paused };
+        }
+        true
     }
 
+    // Waits out the delay between steps, cut short by any change to the controls
+    fn wait_for_delay(&self) {
+        let (control, changed) = &*self.handle.control;
+        let control = control.lock().unwrap();
+        let step_ms = control.step_ms;
+        if step_ms > 0 {
+            let _ = changed.wait_timeout(control, Duration::from_millis(step_ms)).unwrap();
+        }
+    }
 
     pub fn run(&mut self, state: SimulationState) {
 
         let mut state = state;
 
-        while !*self.shutting_down.read().unwrap() {
-
-            while !*self.running.read().unwrap() {
-                println!("Paused");
+        while self.wait_for_step(state.halted) {
+            state = self.simulation.step(state);
+            self.traffic_publisher.publish(&state.traffic);
+            for event in state.events.drain(..) {
+                println!("{}", event);
             }
-
-            while *self.running.read().unwrap() {
-                state = self.simulation.step(state);
-                self.traffic_publisher.publish(&state.traffic);
+            if state.halted {
+                println!("Halted");
             }
+            self.wait_for_delay();
         }
 
     }
 
 }
+
+#[cfg(test)]
+mod tests {
+
+    use std::sync::{Arc, RwLock};
+    use std::sync::mpsc::{Sender, channel};
+    use std::thread;
+    use std::time::Duration;
+    use occupancy::Occupancy;
+    use simulation::{Clock, Simulation, SimulationState, SimulationStep, Simulator, SimulatorHandle, create_rng};
+    use Traffic;
+
+    #[test]
+    fn steps_stamp_traffic_and_tick_clock() {
+        let simulation = Simulation{ steps: vec![] };
+        let state = SimulationState::new(Traffic::new(vec![]), Occupancy::new(1), create_rng(0));
+        let state = SimulationState{ clock: Clock::new(0.5), ..state };
+        let state = simulation.step(simulation.step(state));
+        assert!(state.traffic.step == 1);
+        assert!(state.clock.get_step() == 2);
+        assert!(state.clock.get_seconds() == 1.0);
+    }
+
+    // Reports each step it runs, so tests can wait on the simulator thread
+    struct ReportSteps {
+        steps: Sender<usize>,
+    }
+
+    impl SimulationStep for ReportSteps {
+        fn step(&self, state: SimulationState) -> SimulationState {
+            self.steps.send(state.clock.get_step()).unwrap();
+            state
+        }
+    }
+
+    #[test]
+    fn handle_runs_steps_then_pauses() {
+        let traffic_version = Arc::new(RwLock::new(None));
+        let handle = SimulatorHandle::new(0);
+        let (sender, steps) = channel();
+        let (version, controls) = (Arc::clone(&traffic_version), handle.clone());
+        let thread = thread::spawn(move || {
+            let simulation = Simulation{ steps: vec![Box::new(ReportSteps{ steps: sender })] };
+            let mut simulator = Simulator::new(simulation, &version, controls);
+            simulator.run(SimulationState::new(Traffic::new(vec![]), Occupancy::new(1), create_rng(0)));
+        });
+        let next_step = || steps.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        handle.run_n(3);
+        assert!((0..3).map(|_| next_step()).collect::<Vec<_>>() == vec![0, 1, 2]);
+        // The simulator pauses itself before running the last of the steps
+        assert!(handle.is_paused());
+
+        handle.step();
+        assert!(next_step() == 3);
+
+        handle.shutdown();
+        thread.join().unwrap();
+        assert!(steps.try_recv().is_err());
+        assert!(traffic_version.read().unwrap().as_ref().map(|t: &Arc<Traffic>| t.step) == Some(3));
+    }
+
+}
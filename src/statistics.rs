@@ -0,0 +1,135 @@
+use std::fmt;
+use simulation::Clock;
+use Trip;
+
+// Counts of what happened to vehicles over a run. Travel times are measured in steps, from the step a vehicle spawns
+// to the step it reaches its destination, and include the steps it waited without moving. Vehicles despawned before
+// arriving are counted apart, by how long they waited.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Statistics {
+    pub spawned: usize,
+    pub arrived: usize,
+    pub despawned: usize,
+    // Spawns which were due but found every source in the group blocked
+    pub blocked_spawns: usize,
+    pub total_travel_time: usize,
+    pub total_waited: usize,
+    pub total_waited_before_despawn: usize,
+}
+
+impl Statistics {
+
+    pub fn new() -> Statistics {
+        Statistics::default()
+    }
+
+    pub fn record_spawn(&mut self) {
+        self.spawned += 1;
+    }
+
+    pub fn record_blocked_spawn(&mut self) {
+        self.blocked_spawns += 1;
+    }
+
+    pub fn record_arrival(&mut self, trip: &Trip) {
+        self.arrived += 1;
+        self.total_travel_time += trip.get_travel_time();
+        self.total_waited += trip.waited;
+    }
+
+    pub fn record_despawn(&mut self, trip: &Trip) {
+        self.despawned += 1;
+        self.total_waited_before_despawn += trip.waited;
+    }
+
+    fn get_mean(total: usize, count: usize) -> Option<f64> {
+        if count == 0 {
+            None
+        } else {
+            Some(total as f64 / count as f64)
+        }
+    }
+
+    pub fn get_mean_travel_time(&self) -> Option<f64> {
+        Statistics::get_mean(self.total_travel_time, self.arrived)
+    }
+
+    pub fn get_mean_waited(&self) -> Option<f64> {
+        Statistics::get_mean(self.total_waited, self.arrived)
+    }
+
+    pub fn get_mean_waited_before_despawn(&self) -> Option<f64> {
+        Statistics::get_mean(self.total_waited_before_despawn, self.despawned)
+    }
+
+    // The clock gives the length of the run
+    pub fn to_json(&self, clock: &Clock) -> String {
+        let json = |mean: Option<f64>| match mean {
+            Some(mean) => format!("{:.3}", mean),
+            None => "null".to_string(),
+        };
+        format!("{{\n  \"steps\": {},\n  \"seconds\": {:.3},\n  \"spawned\": {},\n  \"arrived\": {},\n  \"despawned\": {},\n  \"blocked_spawns\": {},\n  \"mean_travel_time\": {},\n  \"mean_waited\": {},\n  \"mean_waited_before_despawn\": {}\n}}\n",
+                clock.get_step(), clock.get_seconds(), self.spawned, self.arrived, self.despawned, self.blocked_spawns,
+                json(self.get_mean_travel_time()), json(self.get_mean_waited()), json(self.get_mean_waited_before_despawn()))
+    }
+
+}
+
+impl fmt::Display for Statistics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Vehicles spawned: {}", self.spawned)?;
+        writeln!(f, "Vehicles arrived: {}", self.arrived)?;
+        writeln!(f, "Vehicles despawned: {}", self.despawned)?;
+        writeln!(f, "Blocked spawns: {}", self.blocked_spawns)?;
+        match self.get_mean_travel_time() {
+            Some(mean) => writeln!(f, "Mean travel time: {:.3} steps", mean)?,
+            None => writeln!(f, "Mean travel time: -")?,
+        }
+        match self.get_mean_waited() {
+            Some(mean) => writeln!(f, "Mean time waiting: {:.3} steps", mean)?,
+            None => writeln!(f, "Mean time waiting: -")?,
+        }
+        match self.get_mean_waited_before_despawn() {
+            Some(mean) => writeln!(f, "Mean time waiting before despawn: {:.3} steps", mean),
+            None => writeln!(f, "Mean time waiting before despawn: -"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use simulation::Clock;
+    use statistics::Statistics;
+    use {Trip, Vehicle};
+
+    fn get_trip(spawn_step: usize, end_step: usize, waited: usize, arrived: bool) -> Trip {
+        let vehicle = Vehicle{ spawn_step, waited, ..Vehicle::new(0, 0, vec![1], 0) };
+        Trip::new(&vehicle, end_step, arrived)
+    }
+
+    #[test]
+    fn travel_time_from_spawn_to_arrival() {
+        let mut statistics = Statistics::new();
+        statistics.record_arrival(&get_trip(0, 3, 1, true));
+        statistics.record_arrival(&get_trip(1, 3, 0, true));
+        statistics.record_despawn(&get_trip(0, 9, 8, false));
+        assert!(statistics.get_mean_travel_time() == Some(2.5));
+        assert!(statistics.get_mean_waited() == Some(0.5));
+        assert!(statistics.get_mean_waited_before_despawn() == Some(8.0));
+    }
+
+    #[test]
+    fn json() {
+        let mut statistics = Statistics::new();
+        let mut clock = Clock::new(0.5);
+        assert!(statistics.to_json(&clock).contains("\"mean_travel_time\": null,\n"));
+        statistics.record_spawn();
+        statistics.record_blocked_spawn();
+        statistics.record_arrival(&get_trip(0, 1, 0, true));
+        clock.tick();
+        clock.tick();
+        assert!(statistics.to_json(&clock) == "{\n  \"steps\": 2,\n  \"seconds\": 1.000,\n  \"spawned\": 1,\n  \"arrived\": 1,\n  \"despawned\": 0,\n  \"blocked_spawns\": 1,\n  \"mean_travel_time\": 1.000,\n  \"mean_waited\": 0.000,\n  \"mean_waited_before_despawn\": null\n}\n");
+    }
+
+}
@@ -1,45 +1,51 @@
 use simulation::VehicleUpdate;
 use Vehicle;
-use occupancy::Occupancy;
+use occupancy::{Granularity, Holder, Occupancy};
 use rand::Rng;
 
 pub struct VehicleFree {
-    block_size: usize,
+    granularity: Granularity,
 }
 
 impl VehicleFree {
     pub fn new(block_size: usize) -> VehicleFree {
-        VehicleFree{ block_size }
+        VehicleFree::with_granularity(Granularity::Block(block_size))
+    }
+
+    pub fn with_granularity(granularity: Granularity) -> VehicleFree {
+        VehicleFree{ granularity }
     }
 }
 
 impl VehicleUpdate for VehicleFree {
     fn update(&self, vehicle: &mut Vehicle, occupancy: &mut Occupancy, _rng: &mut Box<Rng>) {
-        let start = self.block_size * (vehicle.location / self.block_size);
-
-        for offset in 0..self.block_size {
-            occupancy.unlock(start + offset);
+        for node in self.granularity.get_nodes(vehicle.location) {
+            if let Err(e) = occupancy.unlock_as(node, Holder::Vehicle(vehicle.id)) {
+                panic!("{}", e);
+            }
         }
     }
 }
 
 pub struct VehicleOccupy {
-    block_size: usize,
+    granularity: Granularity,
 }
 
 impl VehicleOccupy {
     pub fn new(block_size: usize) -> VehicleOccupy {
-        VehicleOccupy{ block_size }
+        VehicleOccupy::with_granularity(Granularity::Block(block_size))
+    }
+
+    pub fn with_granularity(granularity: Granularity) -> VehicleOccupy {
+        VehicleOccupy{ granularity }
     }
 }
 
 impl VehicleUpdate for VehicleOccupy {
     fn update(&self, vehicle: &mut Vehicle, occupancy: &mut Occupancy, _rng: &mut Box<Rng>) {
         if !vehicle.destination.contains(&vehicle.location) {
-            let start = self.block_size * (vehicle.location / self.block_size);
-
-            for offset in 0..self.block_size {
-                occupancy.lock(start + offset);
+            for node in self.granularity.get_nodes(vehicle.location) {
+                occupancy.lock_as(node, Holder::Vehicle(vehicle.id));
             }
         }
     }
@@ -53,13 +59,13 @@ mod tests {
     use super::VehicleOccupy;
     use simulation::VehicleUpdate;
     use Vehicle;
-    use occupancy::Occupancy;
+    use occupancy::{Granularity, Holder, Occupancy};
     use rand::Rng;
 
     #[test]
     fn free_then_occupy_start_of_range() {
         let occupy = VehicleOccupy::new(3);
-        let mut vehicle = Vehicle{ location: 0, destination: vec![1], destination_index: 0 };
+        let mut vehicle = Vehicle::new(0, 0, vec![1], 0);
         let mut occupancy = Occupancy::new(9);
         occupancy.remove_all_locks(0);
         let mut rng: Box<Rng> = Box::new(rand::thread_rng());
@@ -72,7 +78,7 @@ mod tests {
     #[test]
     fn free_then_occupy_mid_range() {
         let occupy = VehicleOccupy::new(4);
-        let mut vehicle = Vehicle{ location: 5, destination: vec![6], destination_index: 0 };
+        let mut vehicle = Vehicle::new(0, 5, vec![6], 0);
         let mut occupancy = Occupancy::new(12);
         occupancy.remove_all_locks(0);
         let mut rng: Box<Rng> = Box::new(rand::thread_rng());
@@ -86,7 +92,7 @@ mod tests {
     #[test]
     fn free_then_occupy_end_of_range() {
         let occupy = VehicleOccupy::new(5);
-        let mut vehicle = Vehicle{ location: 10, destination: vec![11], destination_index: 0 };
+        let mut vehicle = Vehicle::new(0, 10, vec![11], 0);
         let mut occupancy = Occupancy::new(15);
         occupancy.remove_all_locks(0);
         let mut rng: Box<Rng> = Box::new(rand::thread_rng());
@@ -102,7 +108,7 @@ mod tests {
     fn occupy_then_free_start_of_range() {
         let free = VehicleFree::new(3);
         let occupy = VehicleOccupy::new(3);
-        let mut vehicle = Vehicle{ location: 0, destination: vec![1], destination_index: 0 };
+        let mut vehicle = Vehicle::new(0, 0, vec![1], 0);
         let mut occupancy = Occupancy::new(9);
         let mut rng: Box<Rng> = Box::new(rand::thread_rng());
         occupy.update(&mut vehicle, &mut occupancy, &mut rng);
@@ -116,7 +122,7 @@ mod tests {
     fn occupy_then_free_mid_range() {
         let free = VehicleFree::new(4);
         let occupy = VehicleOccupy::new(4);
-        let mut vehicle = Vehicle{ location: 5, destination: vec![6], destination_index: 0 };
+        let mut vehicle = Vehicle::new(0, 5, vec![6], 0);
         let mut occupancy = Occupancy::new(12);
         let mut rng: Box<Rng> = Box::new(rand::thread_rng());
         occupy.update(&mut vehicle, &mut occupancy, &mut rng);
@@ -131,7 +137,7 @@ mod tests {
     fn occupy_then_free_end_of_range() {
         let free = VehicleFree::new(5);
         let occupy = VehicleOccupy::new(5);
-        let mut vehicle = Vehicle{ location: 10, destination: vec![11], destination_index: 0 };
+        let mut vehicle = Vehicle::new(0, 10, vec![11], 0);
         let mut occupancy = Occupancy::new(15);
         let mut rng: Box<Rng> = Box::new(rand::thread_rng());
         occupy.update(&mut vehicle, &mut occupancy, &mut rng);
@@ -146,7 +152,7 @@ mod tests {
     #[test]
     fn should_not_occupy_destination() {
         let occupy = VehicleOccupy::new(3);
-        let mut vehicle = Vehicle{ location: 0, destination: vec![0, 1], destination_index: 0 };
+        let mut vehicle = Vehicle::new(0, 0, vec![0, 1], 0);
         let mut occupancy = Occupancy::new(9);
         occupancy.remove_all_locks(0);
         let mut rng: Box<Rng> = Box::new(rand::thread_rng());
@@ -155,4 +161,48 @@ mod tests {
         assert!(occupancy.is_unlocked(1));
         assert!(occupancy.is_unlocked(2));
     }
+
+    #[test]
+    fn locks_held_by_vehicle() {
+        let free = VehicleFree::new(2);
+        let occupy = VehicleOccupy::new(2);
+        let mut vehicle = Vehicle::new(7, 3, vec![0], 0);
+        let mut occupancy = Occupancy::with_ownership(4);
+        let mut rng: Box<Rng> = Box::new(rand::thread_rng());
+        occupy.update(&mut vehicle, &mut occupancy, &mut rng);
+        assert!(occupancy.get_locks_held_by(Holder::Vehicle(7)) == vec![2, 3]);
+        free.update(&mut vehicle, &mut occupancy, &mut rng);
+        assert!(occupancy.get_locks_held_by(Holder::Vehicle(7)).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Vehicle(8) tried to unlock node 2 which is held by [Vehicle(7)]")]
+    fn free_locks_held_by_another_vehicle() {
+        let free = VehicleFree::new(2);
+        let mut vehicle = Vehicle::new(8, 3, vec![0], 0);
+        let mut occupancy = Occupancy::with_ownership(4);
+        occupancy.lock_as(2, Holder::Vehicle(7));
+        occupancy.lock_as(3, Holder::Vehicle(7));
+        let mut rng: Box<Rng> = Box::new(rand::thread_rng());
+        free.update(&mut vehicle, &mut occupancy, &mut rng);
+    }
+    #[test]
+    fn occupy_conflict_group() {
+        // North and south share a group, as do east and west
+        let granularity = Granularity::Groups(vec![vec![0, 2], vec![1, 3], vec![0, 2], vec![1, 3]]);
+        let free = VehicleFree::with_granularity(granularity.clone());
+        let occupy = VehicleOccupy::with_granularity(granularity);
+        let mut vehicle = Vehicle::new(0, 2, vec![5], 0);
+        let mut occupancy = Occupancy::new(4);
+        let mut rng: Box<Rng> = Box::new(rand::thread_rng());
+        occupy.update(&mut vehicle, &mut occupancy, &mut rng);
+        assert!(!occupancy.is_unlocked(0));
+        assert!(occupancy.is_unlocked(1));
+        assert!(!occupancy.is_unlocked(2));
+        assert!(occupancy.is_unlocked(3));
+        free.update(&mut vehicle, &mut occupancy, &mut rng);
+        assert!(occupancy.is_unlocked(0));
+        assert!(occupancy.is_unlocked(2));
+    }
+
 }
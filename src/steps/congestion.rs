@@ -0,0 +1,128 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use routes::Routes;
+use simulation::{SimulationState, SimulationStep};
+
+// Counts the vehicles in each block on every step. Every interval steps, each edge is re-weighted to its free flow
+// cost plus penalty times the average number of vehicles seen in the block it leads into, and the cost fields are
+// repaired, so that vehicles route around jams.
+pub struct Congestion {
+    routes: Rc<RefCell<Routes>>,
+    free_flow: Vec<u32>,
+    block_size: usize,
+    interval: usize,
+    penalty: u32,
+    samples: RefCell<Vec<u32>>,
+    steps: Cell<usize>,
+}
+
+impl Congestion {
+
+    pub fn new(routes: Rc<RefCell<Routes>>, block_size: usize, interval: usize, penalty: u32) -> Congestion {
+        let (free_flow, blocks) = {
+            let network = &routes.borrow().network;
+            (network.edges().iter().map(|e| e.cost).collect(), (network.nodes + block_size - 1) / block_size)
+        };
+        Congestion{ routes, free_flow, block_size, interval, penalty, samples: RefCell::new(vec![0; blocks]), steps: Cell::new(0) }
+    }
+
+    fn reweight(&self) {
+        let mut samples = self.samples.borrow_mut();
+        let mut routes = self.routes.borrow_mut();
+        let interval = self.interval as u32;
+        let costs: Vec<u32> = routes.network.edges().iter().zip(self.free_flow.iter())
+            .map(|(edge, free_flow)| free_flow + (self.penalty * samples[edge.to / self.block_size] + interval / 2) / interval)
+            .collect();
+        routes.set_costs(&costs);
+        for sample in samples.iter_mut() {
+            *sample = 0;
+        }
+    }
+
+}
+
+impl SimulationStep for Congestion {
+    fn step(&self, state: SimulationState) -> SimulationState {
+        {
+            let mut samples = self.samples.borrow_mut();
+            for vehicle in state.traffic.vehicles.iter() {
+                samples[vehicle.location / self.block_size] += 1;
+            }
+        }
+        self.steps.set(self.steps.get() + 1);
+        if self.steps.get() == self.interval {
+            self.reweight();
+            self.steps.set(0);
+        }
+        state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use rand;
+    use network::{Edge, Network};
+    use occupancy::Occupancy;
+    use routes::Routes;
+    use simulation::{SimulationState, SimulationStep};
+    use steps::congestion::Congestion;
+    use {Traffic, Vehicle};
+
+    // Two routes from 0 to 3, through 1 (cost 2) or through 2 (cost 3)
+    fn get_test_routes() -> Rc<RefCell<Routes>> {
+        let edges = vec![
+            Edge::new(0, 1, 1),
+            Edge::new(1, 3, 1),
+            Edge::new(0, 2, 2),
+            Edge::new(2, 3, 1)];
+        let network = Network::new(4, &edges);
+        let costs = vec![network.dijkstra(vec![3])];
+        Rc::new(RefCell::new(Routes::new(network, vec![vec![3]], costs)))
+    }
+
+    fn get_state(locations: &[usize]) -> SimulationState {
+        let vehicles = locations.iter().map(|l| Vehicle::new(0, *l, vec![3], 0)).collect();
+        SimulationState::new(Traffic::new(vehicles), Occupancy::new(4), Box::new(rand::thread_rng()))
+    }
+
+    #[test]
+    fn costs_only_change_after_interval() {
+        let routes = get_test_routes();
+        let congestion = Congestion::new(Rc::clone(&routes), 1, 2, 4);
+        let state = congestion.step(get_state(&[1]));
+        assert!(routes.borrow().costs[0][0] == Some(2));
+        congestion.step(state);
+        assert!(routes.borrow().costs[0][0] == Some(3));
+        assert!(routes.borrow().costs[0][1] == Some(1));
+    }
+
+    #[test]
+    fn costs_use_average_occupancy() {
+        let routes = get_test_routes();
+        let congestion = Congestion::new(Rc::clone(&routes), 1, 4, 4);
+        let mut state = get_state(&[1]);
+        for _ in 0..3 {
+            state = congestion.step(state);
+        }
+        state.traffic.vehicles.clear();
+        congestion.step(state);
+        // Node 1 was occupied for 3 of 4 steps, so the edge into it costs 1 + 4 * 3 / 4
+        let expected = vec![4, 2, 1, 1];
+        let costs: Vec<u32> = routes.borrow().network.edges().iter().map(|e| e.cost).collect();
+        assert!(costs == expected);
+    }
+
+    #[test]
+    fn costs_recover_when_congestion_clears() {
+        let routes = get_test_routes();
+        let congestion = Congestion::new(Rc::clone(&routes), 1, 1, 4);
+        let state = congestion.step(get_state(&[1]));
+        assert!(routes.borrow().costs[0][0] == Some(3));
+        congestion.step(SimulationState{ traffic: Traffic::new(vec![]), ..state });
+        assert!(routes.borrow().costs[0][0] == Some(2));
+    }
+
+}
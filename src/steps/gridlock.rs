@@ -0,0 +1,357 @@
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Arc;
+use network::{Edge, Network};
+use city::City;
+use occupancy::{Granularity, Holder, Occupancy};
+use routes::Routes;
+use simulation::{Event, SimulationState, SimulationStep};
+use {Trip, Vehicle};
+
+// Builds a wait-for graph between vehicles and reports each cycle of vehicles which can never move again. A vehicle
+// waits for the vehicles holding the nodes it wants to move into next, the nodes one edge closer to its destination.
+// It can move again if any of those nodes is free or held by something other than a vehicle (traffic lights change),
+// or if any vehicle it waits for can move. A cycle is only reported on the step it forms, but is resolved on every
+// step it remains.
+pub struct GridlockDetector {
+    city: Arc<City>,
+    routes: Rc<RefCell<Routes>>,
+    granularity: Granularity,
+    resolution: Resolution,
+    reported: RefCell<Vec<Vec<usize>>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Resolution {
+    // Only report gridlocks
+    Report,
+    // Stop the simulation at the first gridlock
+    Freeze,
+    // Remove the vehicle in the cycle which has waited longest
+    DespawnLongestWaiting,
+    // Move the vehicle in the cycle which has waited longest into any free neighbouring cell, even if it is further
+    // from its destination. Falls back to despawning when no vehicle in the cycle has a free neighbour.
+    NonImprovingMove,
+}
+
+impl GridlockDetector {
+
+    pub fn new(city: Arc<City>, routes: Rc<RefCell<Routes>>, granularity: Granularity, resolution: Resolution) -> GridlockDetector {
+        GridlockDetector{
+            city,
+            routes,
+            granularity,
+            resolution,
+            reported: RefCell::new(vec![]),
+        }
+    }
+
+    // Ties are broken by the lowest id, so that runs are repeatable
+    fn get_longest_waiting(&self, vehicles: &[Vehicle], gridlock: &[usize]) -> usize {
+        *gridlock.iter()
+            .max_by_key(|id| (vehicles.iter().find(|v| v.id == **id).unwrap().waited, Reverse(**id)))
+            .unwrap()
+    }
+
+    fn free(&self, vehicle: &Vehicle, occupancy: &mut Occupancy) {
+        for node in self.granularity.get_nodes(vehicle.location) {
+            if let Err(e) = occupancy.unlock_as(node, Holder::Vehicle(vehicle.id)) {
+                panic!("{}", e);
+            }
+        }
+    }
+
+    fn occupy(&self, vehicle: &Vehicle, occupancy: &mut Occupancy) {
+        if !vehicle.destination.contains(&vehicle.location) {
+            for node in self.granularity.get_nodes(vehicle.location) {
+                occupancy.lock_as(node, Holder::Vehicle(vehicle.id));
+            }
+        }
+    }
+
+    // A free node next to the vehicle and outside the nodes it locks
+    fn get_free_neighbour(&self, vehicle: &Vehicle, occupancy: &Occupancy) -> Option<usize> {
+        let routes = self.routes.borrow();
+        routes.network.get_out(vehicle.location).iter()
+            .map(|e| e.to)
+            .filter(|&n| self.granularity.get_key(n) != self.granularity.get_key(vehicle.location))
+            .filter(|&n| occupancy.is_unlocked(n))
+            .filter_map(|n| routes.get_cost(vehicle.destination_index, n).map(|c| (c, n)))
+            .min_by_key(|&(c, _)| c)
+            .map(|(_, n)| n)
+    }
+
+    fn despawn(&self, state: &mut SimulationState, id: usize, step: usize) {
+        let index = state.traffic.vehicles.iter().position(|v| v.id == id).unwrap();
+        let vehicle = state.traffic.vehicles.remove(index);
+        self.free(&vehicle, &mut state.occupancy);
+        let trip = Trip::new(&vehicle, step, false);
+        state.statistics.record_despawn(&trip);
+        state.traffic.completed.push(trip);
+        state.events.push(Event::Despawned{ step, vehicle: id, cell: self.city.get_cell(vehicle.location) });
+    }
+
+    fn resolve(&self, state: &mut SimulationState, gridlock: &[usize], step: usize) {
+        match self.resolution {
+            Resolution::Report => {},
+            Resolution::Freeze => state.halted = true,
+            Resolution::DespawnLongestWaiting => {
+                let id = self.get_longest_waiting(&state.traffic.vehicles, gridlock);
+                self.despawn(state, id, step);
+            },
+            Resolution::NonImprovingMove => {
+                let movable: Vec<usize> = gridlock.iter().cloned()
+                    .filter(|id| {
+                        let vehicle = state.traffic.vehicles.iter().find(|v| v.id == *id).unwrap();
+                        self.get_free_neighbour(vehicle, &state.occupancy).is_some()
+                    })
+                    .collect();
+                if movable.is_empty() {
+                    let id = self.get_longest_waiting(&state.traffic.vehicles, gridlock);
+                    return self.despawn(state, id, step);
+                }
+                let id = self.get_longest_waiting(&state.traffic.vehicles, &movable);
+                let vehicle = state.traffic.vehicles.iter_mut().find(|v| v.id == id).unwrap();
+                let to = self.get_free_neighbour(vehicle, &state.occupancy).unwrap();
+                let from = self.city.get_cell(vehicle.location);
+                self.free(vehicle, &mut state.occupancy);
+                vehicle.location = to;
+                // Vehicles are updated before gridlocks are found, so this step was counted as a wait
+                vehicle.waited = vehicle.waited.saturating_sub(1);
+                vehicle.moves += 1;
+                self.occupy(vehicle, &mut state.occupancy);
+                state.events.push(Event::Moved{ step, vehicle: id, from, to: self.city.get_cell(to) });
+            },
+        }
+    }
+
+    fn get_next_nodes(&self, vehicle: &Vehicle) -> Vec<usize> {
+        let routes = self.routes.borrow();
+        let target = vehicle.destination_index;
+        match routes.get_cost(target, vehicle.location) {
+            Some(cost) if !vehicle.destination.contains(&vehicle.location) => routes.network.get_out(vehicle.location).iter()
+                .filter(|e| routes.get_cost(target, e.to).is_some_and(|c| c + e.cost == cost))
+                .map(|e| e.to)
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    // The vehicles (by index) holding a node, or None if no vehicle holds it
+    fn get_blockers(&self, node: usize, occupancy: &Occupancy, vehicles: &[Vehicle], by_id: &HashMap<usize, usize>) -> Option<Vec<usize>> {
+        if occupancy.is_unlocked(node) {
+            return None;
+        }
+        let blockers: Vec<usize> = if occupancy.is_tracking_ownership() {
+            occupancy.get_holders(node).iter().filter_map(|h| match h {
+                Holder::Vehicle(id) => by_id.get(id).cloned(),
+                _ => None,
+            }).collect()
+        } else {
+            let key = self.granularity.get_key(node);
+            (0..vehicles.len()).filter(|&i| self.granularity.get_key(vehicles[i].location) == key).collect()
+        };
+        if blockers.is_empty() { None } else { Some(blockers) }
+    }
+
+    // For each vehicle, the vehicles it is waiting for, or None if it is free to move
+    fn get_waits(&self, vehicles: &[Vehicle], occupancy: &Occupancy) -> Vec<Option<Vec<usize>>> {
+        let by_id: HashMap<usize, usize> = vehicles.iter().enumerate().map(|(i, v)| (v.id, i)).collect();
+        vehicles.iter().enumerate().map(|(i, vehicle)| {
+            let next_nodes = self.get_next_nodes(vehicle);
+            if next_nodes.is_empty() {
+                return None;
+            }
+            let mut waits = vec![];
+            for node in next_nodes {
+                match self.get_blockers(node, occupancy, vehicles, &by_id) {
+                    Some(blockers) if !blockers.contains(&i) => waits.extend(blockers),
+                    _ => return None,
+                }
+            }
+            waits.sort();
+            waits.dedup();
+            Some(waits)
+        }).collect()
+    }
+
+    // Groups of vehicles (by index) which wait for each other in a cycle and can never move
+    fn find_gridlocks(&self, vehicles: &[Vehicle], occupancy: &Occupancy) -> Vec<Vec<usize>> {
+        let waits = self.get_waits(vehicles, occupancy);
+        let mut stuck: Vec<bool> = waits.iter().map(|w| w.is_some()).collect();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (i, wait) in waits.iter().enumerate() {
+                if stuck[i] && wait.as_ref().is_some_and(|w| w.iter().any(|&j| !stuck[j])) {
+                    stuck[i] = false;
+                    changed = true;
+                }
+            }
+        }
+
+        let edges: Vec<Edge<u32>> = waits.iter().enumerate()
+            .filter(|&(i, _)| stuck[i])
+            .flat_map(|(i, wait)| wait.iter().flatten().map(move |&j| Edge::new(i, j, 1)))
+            .collect();
+        let network = Network::new(vehicles.len(), &edges);
+        network.strongly_connected_components().into_iter()
+            .filter(|c| c.len() > 1)
+            .collect()
+    }
+
+}
+
+impl SimulationStep for GridlockDetector {
+    fn step(&self, state: SimulationState) -> SimulationState {
+        let mut state = state;
+        let step = state.clock.get_step();
+
+        let vehicles = &state.traffic.vehicles;
+        let mut gridlocks: Vec<Vec<usize>> = self.find_gridlocks(vehicles, &state.occupancy).into_iter()
+            .map(|g| g.into_iter().map(|i| vehicles[i].id).collect())
+            .collect();
+        for gridlock in gridlocks.iter_mut() {
+            gridlock.sort();
+        }
+        gridlocks.sort();
+        let mut reported = self.reported.borrow_mut();
+        for gridlock in gridlocks.iter().filter(|g| !reported.contains(g)) {
+            let cells = gridlock.iter()
+                .map(|id| vehicles.iter().find(|v| v.id == *id).unwrap())
+                .map(|v| self.city.get_cell(v.location))
+                .collect();
+            state.events.push(Event::Gridlock{ step, vehicles: gridlock.clone(), cells });
+        }
+        for gridlock in gridlocks.iter() {
+            self.resolve(&mut state, gridlock, step);
+        }
+        *reported = gridlocks;
+        state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use rand;
+    use network::{Edge, Network};
+    use city::City;
+    use occupancy::{Granularity, Holder, Occupancy};
+    use routes::Routes;
+    use simulation::{Event, SimulationState, SimulationStep};
+    use steps::gridlock::{GridlockDetector, Resolution};
+    use {Traffic, Vehicle};
+
+    // A ring 0 -> 1 -> 2 -> 3 -> 0 where vehicles at 0 and 2 want to reach 2 and 0, with a slow detour 1 -> 4 -> 2
+    fn get_detector(resolution: Resolution) -> GridlockDetector {
+        let edges = vec![Edge::new(0, 1, 1), Edge::new(1, 2, 1), Edge::new(2, 3, 1), Edge::new(3, 0, 1), Edge::new(1, 4, 3), Edge::new(4, 2, 3)];
+        let network = Network::new(5, &edges);
+        let targets = vec![vec![2], vec![0]];
+        let costs = targets.iter().map(|t| network.dijkstra(t.clone())).collect();
+        let routes = Rc::new(RefCell::new(Routes::new(network, targets, costs)));
+        GridlockDetector::new(Arc::new(City::new(2, 1)), routes, Granularity::Node, resolution)
+    }
+
+    // Vehicles given as (id, location, destination index), each locking its own node
+    fn get_state(vehicles: &[(usize, usize, usize)], occupancy: Occupancy) -> SimulationState {
+        let mut occupancy = occupancy;
+        let vehicles = vehicles.iter().map(|&(id, location, destination_index)| {
+            occupancy.lock_as(location, Holder::Vehicle(id));
+            let destination = if destination_index == 0 { vec![2] } else { vec![0] };
+            Vehicle::new(id, location, destination, destination_index)
+        }).collect();
+        SimulationState::new(Traffic::new(vehicles), occupancy, Box::new(rand::thread_rng()))
+    }
+
+    #[test]
+    fn reports_cycle_once() {
+        let detector = get_detector(Resolution::Report);
+        let mut state = detector.step(get_state(&[(7, 0, 0), (8, 1, 0), (9, 2, 1), (3, 3, 1)], Occupancy::with_ownership(5)));
+        assert!(state.events.len() == 1);
+        match state.events[0] {
+            Event::Gridlock{ step, ref vehicles, ref cells } => {
+                assert!(step == 0);
+                assert!(*vehicles == vec![3, 7, 8, 9]);
+                assert!(cells.len() == 4);
+            },
+            _ => panic!("Expected a gridlock"),
+        }
+        assert!(!state.halted);
+        state.events.clear();
+        let state = detector.step(state);
+        assert!(state.events.is_empty());
+    }
+
+    #[test]
+    fn freezes_cycle_without_ownership() {
+        let detector = get_detector(Resolution::Freeze);
+        let state = detector.step(get_state(&[(7, 0, 0), (8, 1, 0), (9, 2, 1), (3, 3, 1)], Occupancy::new(5)));
+        assert!(state.events.len() == 1);
+        assert!(state.halted);
+    }
+
+    #[test]
+    fn no_gridlock_with_free_node() {
+        let detector = get_detector(Resolution::Freeze);
+        let state = detector.step(get_state(&[(7, 0, 0), (8, 1, 0), (9, 2, 1)], Occupancy::with_ownership(5)));
+        assert!(state.events.is_empty());
+        assert!(!state.halted);
+    }
+
+    #[test]
+    fn no_gridlock_when_waiting_for_traffic_light() {
+        let detector = get_detector(Resolution::Report);
+        let mut occupancy = Occupancy::with_ownership(5);
+        occupancy.lock_as(3, Holder::TrafficLight(0));
+        let state = detector.step(get_state(&[(7, 0, 0), (8, 1, 0), (9, 2, 1)], occupancy));
+        assert!(state.events.is_empty());
+    }
+
+    #[test]
+    fn despawns_longest_waiting() {
+        let detector = get_detector(Resolution::DespawnLongestWaiting);
+        let mut state = get_state(&[(7, 0, 0), (8, 1, 0), (9, 2, 1), (3, 3, 1)], Occupancy::with_ownership(5));
+        for (vehicle, waited) in state.traffic.vehicles.iter_mut().zip([1, 2, 2, 0].iter()) {
+            vehicle.waited = *waited;
+        }
+        let state = detector.step(state);
+        // 8 and 9 have waited longest, and 8 has the lower id
+        assert!(state.events[1] == Event::Despawned{ step: 0, vehicle: 8, cell: City::new(2, 1).get_cell(1) });
+        assert!(state.traffic.vehicles.iter().map(|v| v.id).collect::<Vec<usize>>() == vec![7, 9, 3]);
+        assert!(state.occupancy.is_unlocked(1));
+        assert!(state.traffic.completed.iter().map(|t| (t.vehicle, t.arrived)).collect::<Vec<_>>() == vec![(8, false)]);
+        assert!(state.statistics.despawned == 1);
+    }
+
+    #[test]
+    fn moves_away_from_destination() {
+        let detector = get_detector(Resolution::NonImprovingMove);
+        let mut state = get_state(&[(7, 0, 0), (8, 1, 0), (9, 2, 1), (3, 3, 1)], Occupancy::with_ownership(5));
+        for vehicle in state.traffic.vehicles.iter_mut() {
+            vehicle.waited = 1;
+        }
+        let state = detector.step(state);
+        assert!(state.events.len() == 2);
+        assert!(state.traffic.vehicles[1].location == 4);
+        assert!(state.traffic.vehicles[1].moves == 1 && state.traffic.vehicles[1].waited == 0);
+        assert!(state.occupancy.is_unlocked(1));
+        assert!(state.occupancy.get_holders(4) == [Holder::Vehicle(8)]);
+    }
+
+    #[test]
+    fn despawns_when_no_vehicle_can_move() {
+        let detector = get_detector(Resolution::NonImprovingMove);
+        let mut occupancy = Occupancy::with_ownership(5);
+        occupancy.lock(4);
+        let state = detector.step(get_state(&[(7, 0, 0), (8, 1, 0), (9, 2, 1), (3, 3, 1)], occupancy));
+        assert!(state.events[1] == Event::Despawned{ step: 0, vehicle: 3, cell: City::new(2, 1).get_cell(3) });
+        assert!(state.traffic.vehicles.len() == 3);
+    }
+
+}
@@ -1,23 +1,24 @@
-use network::Network;
+use std::cell::RefCell;
+use std::rc::Rc;
 use occupancy::Occupancy;
+use routes::Routes;
 use simulation::VehicleUpdate;
 use Vehicle;
 use rand::Rng;
 
 pub struct LookaheadDriver {
     lookahead: usize,
-    network: Network,
-    costs: Vec<Vec<Option<u32>>>,
+    routes: Rc<RefCell<Routes>>,
 }
 
 impl LookaheadDriver {
 
-    pub fn new(lookahead: usize, network: Network, costs: Vec<Vec<Option<u32>>>) -> LookaheadDriver {
-        LookaheadDriver{ lookahead, network, costs }
+    pub fn new(lookahead: usize, routes: Rc<RefCell<Routes>>) -> LookaheadDriver {
+        LookaheadDriver{ lookahead, routes }
     }
 
-    fn extend(&self, path: &Vec<usize>, occupancy: &Occupancy) -> Vec<Vec<usize>> {
-        let neighbours: Vec<usize> = self.network.get_out(*path.last().unwrap()).iter().map(|n| n.to).collect();
+    fn extend(&self, routes: &Routes, path: &Vec<usize>, occupancy: &Occupancy) -> Vec<Vec<usize>> {
+        let neighbours: Vec<usize> = routes.network.get_out(*path.last().unwrap()).iter().map(|n| n.to).collect();
         let free_neighbours: Vec<usize> = neighbours.into_iter()
             .filter(|n| { occupancy.is_unlocked(*n) && !path.contains(n) })
             .collect();
@@ -30,11 +31,11 @@ impl LookaheadDriver {
         out
     }
 
-    fn extend_all(&self, paths: &mut Vec<Vec<usize>>, length_to_extend: usize, occupancy: &Occupancy) -> Vec<Vec<usize>> {
+    fn extend_all(&self, routes: &Routes, paths: &mut Vec<Vec<usize>>, length_to_extend: usize, occupancy: &Occupancy) -> Vec<Vec<usize>> {
         let mut paths_out = vec![];
         for path in paths.iter() {
             if path.len() == length_to_extend {
-                paths_out.append(&mut self.extend(path, occupancy));
+                paths_out.append(&mut self.extend(routes, path, occupancy));
             }
         }
         paths_out.append(paths);
@@ -45,20 +46,21 @@ impl LookaheadDriver {
 
 impl VehicleUpdate for LookaheadDriver {
     fn update(&self, vehicle: &mut Vehicle, occupancy: &mut Occupancy, rng: &mut Box<Rng>) {
-        let costs = &self.costs[vehicle.destination_index];
+        let routes = self.routes.borrow();
+        let target = vehicle.destination_index;
         let node = vehicle.location;
-        if let Some(current_cost) = costs[node] {
+        if let Some(current_cost) = routes.get_cost(target, node) {
             let mut paths = vec![vec![node]];
             for i in 0..self.lookahead {
-                paths = self.extend_all(&mut paths, i + 1, &occupancy);
+                paths = self.extend_all(&routes, &mut paths, i + 1, &occupancy);
             }
             let lowest_cost = paths.iter()
-                .filter_map(|p| costs[*p.last().unwrap()])
+                .filter_map(|p| routes.get_cost(target, *p.last().unwrap()))
                 .min();
             if let Some(lowest_cost) = lowest_cost {
                 if lowest_cost < current_cost {
                     let candidates: Vec<Vec<usize>> = paths.into_iter()
-                        .filter(|p| costs[*p.last().unwrap()] == Some(lowest_cost))
+                        .filter(|p| routes.get_cost(target, *p.last().unwrap()) == Some(lowest_cost))
                         .collect();
                     let shortest = candidates.iter()
                         .map(|p| p.len())
@@ -80,22 +82,25 @@ mod tests {
     extern crate rand;
 
     use super::Vehicle;
+    use std::cell::RefCell;
+    use std::rc::Rc;
     use network::{Edge, Network};
+    use routes::Routes;
     use simulation::VehicleUpdate;
     use steps::lookahead_driver::LookaheadDriver;
     use occupancy::Occupancy;
     use rand::Rng;
 
     fn get_test_driver(lookahead: usize, destination: Vec<usize>) -> LookaheadDriver {
-        let edges = Edge::create_grid(4, 4, 1, Edge::create_4_neighbour_deltas());
+        let edges = Edge::create_grid(4, 4, 1, Edge::<u32>::create_4_neighbour_deltas());
         let network = Network::new(16, &edges);
-        let costs = vec![network.dijkstra(destination)];
-        LookaheadDriver::new(lookahead, network, costs)
+        let costs = vec![network.dijkstra(destination.clone())];
+        LookaheadDriver::new(lookahead, Rc::new(RefCell::new(Routes::new(network, vec![destination], costs))))
     }
 
     fn init(lookahead: usize, vehicle: usize, destination: Vec<usize>) -> (LookaheadDriver, Vehicle, Occupancy, Box<Rng>) {
         let driver = get_test_driver(lookahead, destination.clone());
-        let vehicle = Vehicle{ location: vehicle, destination, destination_index: 0 };
+        let vehicle = Vehicle::new(0, vehicle, destination, 0);
         let occupancy = Occupancy::new(16);
         let rng: Box<Rng> = Box::new(rand::thread_rng());
         (driver, vehicle, occupancy, rng)
@@ -159,6 +164,19 @@ mod tests {
         assert!(vehicle.location == 1);
     }
 
+    #[test]
+    fn lookahead_with_hierarchy() {
+        let edges = Edge::create_grid(4, 4, 1, Edge::<u32>::create_4_neighbour_deltas());
+        let routes = Routes::with_hierarchy(Network::new(16, &edges), vec![vec![13]]);
+        let driver = LookaheadDriver::new(3, Rc::new(RefCell::new(routes)));
+        let (_, mut vehicle, mut occupancy, mut rng) = init(3, 1, vec![13]);
+
+        occupancy.lock(4);
+        occupancy.lock(5);
+        driver.update(&mut vehicle, &mut occupancy, &mut rng);
+        assert!(vehicle.location == 2);
+    }
+
     #[test]
     fn two_routes_a() {
         let (driver, mut vehicle, mut occupancy, mut rng) = init(3, 1, vec![13]);
@@ -261,8 +279,8 @@ mod tests {
             Edge::new(4, 7, 1)];
         let network = Network::new(8, &edges);
         let costs = vec![network.dijkstra(vec![7])];
-        let driver = LookaheadDriver::new(3, network, costs);
-        let mut vehicle = Vehicle{ location: 1, destination: vec![7], destination_index: 0 };
+        let driver = LookaheadDriver::new(3, Rc::new(RefCell::new(Routes::new(network, vec![vec![7]], costs))));
+        let mut vehicle = Vehicle::new(0, 1, vec![7], 0);
         let mut occupancy = Occupancy::new(16);
         let mut rng: Box<Rng> = Box::new(rand::thread_rng());
 
@@ -283,8 +301,8 @@ mod tests {
             Edge::new(4, 7, 1)];
         let network = Network::new(8, &edges);
         let costs = vec![network.dijkstra(vec![7])];
-        let driver = LookaheadDriver::new(3, network, costs);
-        let mut vehicle = Vehicle{ location: 1, destination: vec![7], destination_index: 0 };
+        let driver = LookaheadDriver::new(3, Rc::new(RefCell::new(Routes::new(network, vec![vec![7]], costs))));
+        let mut vehicle = Vehicle::new(0, 1, vec![7], 0);
         let mut occupancy = Occupancy::new(16);
         let mut rng: Box<Rng> = Box::new(rand::thread_rng());
 
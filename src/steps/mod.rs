@@ -1,4 +1,7 @@
 pub mod lookahead_driver;
 pub mod block_occupier;
-pub mod delay;
 pub mod traffic_lights;
+pub mod congestion;
+pub mod gridlock;
+pub mod reservation_booker;
+pub mod spawner;
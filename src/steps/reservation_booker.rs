@@ -0,0 +1,215 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use occupancy::{Granularity, Holder, Occupancy};
+use reservations::Reservations;
+use routes::Routes;
+use simulation::{SimulationState, SimulationStep, VehicleUpdate};
+use Vehicle;
+use rand::Rng;
+
+// An alternative to VehicleFree and VehicleOccupy where vehicles also book the nodes along their route for the
+// coming steps, taking the same nodes at each step as VehicleOccupy would. Bookings for the current and next step are
+// locked in the occupancy, so that other vehicles cannot move into nodes which have been promised to someone else,
+// while later bookings only stop other vehicles booking the same nodes for the same step.
+pub struct AdvanceReservations {
+    reservations: Rc<RefCell<Reservations>>,
+}
+
+impl AdvanceReservations {
+    pub fn new(reservations: Rc<RefCell<Reservations>>) -> AdvanceReservations {
+        AdvanceReservations{ reservations }
+    }
+}
+
+impl SimulationStep for AdvanceReservations {
+    fn step(&self, state: SimulationState) -> SimulationState {
+        let mut state = state;
+        let mut reservations = self.reservations.borrow_mut();
+        for (node, vehicle) in reservations.advance() {
+            if let Err(e) = state.occupancy.unlock_as(node, Holder::Vehicle(vehicle)) {
+                panic!("{}", e);
+            }
+        }
+        for (node, vehicle) in reservations.get_bookings(1) {
+            state.occupancy.lock_as(node, Holder::Vehicle(vehicle));
+        }
+        state
+    }
+}
+
+pub struct VehicleCancel {
+    granularity: Granularity,
+    reservations: Rc<RefCell<Reservations>>,
+}
+
+impl VehicleCancel {
+    pub fn new(granularity: Granularity, reservations: Rc<RefCell<Reservations>>) -> VehicleCancel {
+        VehicleCancel{ granularity, reservations }
+    }
+}
+
+impl VehicleUpdate for VehicleCancel {
+    fn update(&self, vehicle: &mut Vehicle, occupancy: &mut Occupancy, _rng: &mut Box<Rng>) {
+        let cancelled = self.reservations.borrow_mut().cancel(vehicle.id);
+        let locked = cancelled.into_iter().filter(|b| b.1 <= 1).map(|b| b.0);
+
+        for node in self.granularity.get_nodes(vehicle.location).into_iter().chain(locked) {
+            if let Err(e) = occupancy.unlock_as(node, Holder::Vehicle(vehicle.id)) {
+                panic!("{}", e);
+            }
+        }
+    }
+}
+
+pub struct VehicleBook {
+    granularity: Granularity,
+    reservations: Rc<RefCell<Reservations>>,
+    routes: Rc<RefCell<Routes>>,
+}
+
+impl VehicleBook {
+
+    pub fn new(granularity: Granularity, reservations: Rc<RefCell<Reservations>>, routes: Rc<RefCell<Routes>>) -> VehicleBook {
+        VehicleBook{ granularity, reservations, routes }
+    }
+
+    // The next node on the shortest route to the vehicle's destination
+    fn get_next(&self, routes: &Routes, vehicle: &Vehicle, node: usize) -> Option<usize> {
+        let target = vehicle.destination_index;
+        let cost = routes.get_cost(target, node)?;
+        routes.network.get_out(node).iter()
+            .find(|e| routes.get_cost(target, e.to).is_some_and(|c| c + e.cost == cost))
+            .map(|e| e.to)
+    }
+
+}
+
+impl VehicleUpdate for VehicleBook {
+    fn update(&self, vehicle: &mut Vehicle, occupancy: &mut Occupancy, _rng: &mut Box<Rng>) {
+        if vehicle.destination.contains(&vehicle.location) {
+            return;
+        }
+        let key = self.granularity.get_key(vehicle.location);
+        for node in self.granularity.get_nodes(vehicle.location) {
+            occupancy.lock_as(node, Holder::Vehicle(vehicle.id));
+        }
+
+        let routes = self.routes.borrow();
+        let mut reservations = self.reservations.borrow_mut();
+        let mut node = vehicle.location;
+        for slot in 1..reservations.get_horizon() + 1 {
+            node = match self.get_next(&routes, vehicle, node) {
+                Some(next) if !vehicle.destination.contains(&next) => next,
+                _ => break,
+            };
+            let nodes = self.granularity.get_nodes(node);
+            let free = nodes.iter().all(|n| reservations.is_free_for(*n, slot, vehicle.id)
+                && (slot > 1 || self.granularity.get_key(node) == key || occupancy.is_unlocked(*n)));
+            if !free {
+                break;
+            }
+            for n in nodes {
+                reservations.book(n, slot, vehicle.id);
+                if slot == 1 {
+                    occupancy.lock_as(n, Holder::Vehicle(vehicle.id));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use rand;
+    use rand::Rng;
+    use network::{Edge, Network};
+    use occupancy::{Granularity, Holder, Occupancy};
+    use reservations::Reservations;
+    use routes::Routes;
+    use simulation::{SimulationState, SimulationStep, VehicleUpdate};
+    use steps::reservation_booker::{AdvanceReservations, VehicleBook, VehicleCancel};
+    use {Traffic, Vehicle};
+
+    // A line 0 -> 1 -> ... -> 5 towards 5
+    fn get_routes() -> Rc<RefCell<Routes>> {
+        let edges: Vec<Edge<u32>> = (0..5).map(|n| Edge::new(n, n + 1, 1)).collect();
+        let network = Network::new(6, &edges);
+        let costs = vec![network.dijkstra(vec![5])];
+        Rc::new(RefCell::new(Routes::new(network, vec![vec![5]], costs)))
+    }
+
+    fn get_vehicle(id: usize, location: usize) -> Vehicle {
+        Vehicle::new(id, location, vec![5], 0)
+    }
+
+    #[test]
+    fn books_ahead_and_locks_next_step() {
+        let reservations = Rc::new(RefCell::new(Reservations::new(3)));
+        let book = VehicleBook::new(Granularity::Node, Rc::clone(&reservations), get_routes());
+        let mut occupancy = Occupancy::with_ownership(6);
+        let mut rng: Box<Rng> = Box::new(rand::thread_rng());
+        book.update(&mut get_vehicle(7, 0), &mut occupancy, &mut rng);
+        assert!(reservations.borrow().get_bookings(1) == vec![(1, 7)]);
+        assert!(reservations.borrow().get_bookings(3) == vec![(3, 7)]);
+        assert!(occupancy.get_holders(0) == [Holder::Vehicle(7)]);
+        assert!(occupancy.get_holders(1) == [Holder::Vehicle(7)]);
+        assert!(occupancy.is_unlocked(2));
+    }
+
+    #[test]
+    fn stops_booking_at_conflict() {
+        let reservations = Rc::new(RefCell::new(Reservations::new(3)));
+        reservations.borrow_mut().book(2, 2, 8);
+        let book = VehicleBook::new(Granularity::Node, Rc::clone(&reservations), get_routes());
+        let mut occupancy = Occupancy::new(6);
+        let mut rng: Box<Rng> = Box::new(rand::thread_rng());
+        book.update(&mut get_vehicle(7, 0), &mut occupancy, &mut rng);
+        assert!(reservations.borrow().get(1, 1) == Some(7));
+        assert!(reservations.borrow().get(3, 3).is_none());
+    }
+
+    #[test]
+    fn does_not_book_destination() {
+        let reservations = Rc::new(RefCell::new(Reservations::new(3)));
+        let book = VehicleBook::new(Granularity::Node, Rc::clone(&reservations), get_routes());
+        let mut occupancy = Occupancy::new(6);
+        let mut rng: Box<Rng> = Box::new(rand::thread_rng());
+        book.update(&mut get_vehicle(7, 3), &mut occupancy, &mut rng);
+        assert!(reservations.borrow().get_bookings(1) == vec![(4, 7)]);
+        assert!(reservations.borrow().get_bookings(2).is_empty());
+    }
+
+    #[test]
+    fn cancel_releases_locks() {
+        let reservations = Rc::new(RefCell::new(Reservations::new(3)));
+        let book = VehicleBook::new(Granularity::Node, Rc::clone(&reservations), get_routes());
+        let cancel = VehicleCancel::new(Granularity::Node, Rc::clone(&reservations));
+        let mut occupancy = Occupancy::with_ownership(6);
+        let mut rng: Box<Rng> = Box::new(rand::thread_rng());
+        let mut vehicle = get_vehicle(7, 0);
+        book.update(&mut vehicle, &mut occupancy, &mut rng);
+        cancel.update(&mut vehicle, &mut occupancy, &mut rng);
+        assert!((0..6).all(|n| occupancy.is_unlocked(n)));
+        assert!(reservations.borrow().get_bookings(3).is_empty());
+    }
+
+    #[test]
+    fn advance_locks_the_new_next_step() {
+        let reservations = Rc::new(RefCell::new(Reservations::new(3)));
+        let book = VehicleBook::new(Granularity::Node, Rc::clone(&reservations), get_routes());
+        let advance = AdvanceReservations::new(Rc::clone(&reservations));
+        let mut state = SimulationState::new(Traffic::new(vec![]), Occupancy::with_ownership(6), Box::new(rand::thread_rng()));
+        book.update(&mut get_vehicle(7, 0), &mut state.occupancy, &mut state.rng);
+        let state = advance.step(state);
+        // The booking of 1 is now for the current step and 2 for the next, so both are locked
+        assert!(state.occupancy.get_holders(1) == [Holder::Vehicle(7)]);
+        assert!(state.occupancy.get_holders(2) == [Holder::Vehicle(7)]);
+        assert!(state.occupancy.is_unlocked(3));
+        let state = advance.step(state);
+        assert!(state.occupancy.is_unlocked(1));
+    }
+
+}
@@ -0,0 +1,164 @@
+use std::cell::Cell;
+use std::sync::Arc;
+use city::City;
+use occupancy::{Granularity, Holder};
+use simulation::{SimulationState, SimulationStep};
+use {Trip, Vehicle};
+use rand::Rng;
+
+pub struct SpawnVehicles {
+    city: Arc<City>,
+    granularity: Granularity,
+    frequency: usize,
+    next_id: Cell<usize>,
+}
+
+impl SpawnVehicles {
+    pub fn new(city: Arc<City>, granularity: Granularity, frequency: usize) -> SpawnVehicles {
+        SpawnVehicles{ city, granularity, frequency, next_id: Cell::new(0) }
+    }
+}
+
+impl SimulationStep for SpawnVehicles {
+    fn step(&self, state: SimulationState) -> SimulationState {
+        let mut traffic = state.traffic;
+        let mut occupancy = state.occupancy;
+        let mut rng = state.rng;
+        let mut statistics = state.statistics;
+        for (group, source) in self.city.sources.iter().enumerate() {
+            if rng.gen_range(0, self.frequency) == 0 {
+                let candidates: Vec<usize> = source.iter()
+                   .cloned()
+                   .filter(|s| occupancy.is_unlocked(*s))
+                   .collect();
+                if candidates.is_empty() {
+                    statistics.record_blocked_spawn();
+                } else {
+                    let location = rng.choose(&candidates).unwrap();
+                    let destination_index = rng.gen_range(0, self.city.destinations.len());
+                    let destination = self.city.destinations.get(destination_index).unwrap().clone();
+                    let id = self.next_id.get();
+                    self.next_id.set(id + 1);
+                    let spawn_step = state.clock.get_step();
+                    traffic.vehicles.push(Vehicle{ origin_group: group, spawn_step, ..Vehicle::new(id, *location, destination, destination_index) });
+                    statistics.record_spawn();
+                    for node in self.granularity.get_nodes(*location) {
+                        occupancy.lock_as(node, Holder::Vehicle(id));
+                    }
+                }
+            }
+        }
+        SimulationState{traffic, occupancy, rng, statistics, ..state}
+    }
+}
+
+pub struct RemoveVehicles {
+}
+
+impl SimulationStep for RemoveVehicles {
+    fn step(&self, state: SimulationState) -> SimulationState {
+        let mut traffic = state.traffic;
+        let occupancy = state.occupancy;
+        let rng = state.rng;
+        let mut statistics = state.statistics;
+        let mut vehicles_next = vec![];
+        for vehicle in traffic.vehicles {
+            if vehicle.destination.contains(&vehicle.location) {
+                let trip = Trip::new(&vehicle, state.clock.get_step(), true);
+                statistics.record_arrival(&trip);
+                traffic.completed.push(trip);
+            } else {
+                vehicles_next.push(vehicle.clone());
+            }
+        }
+        traffic.vehicles = vehicles_next;
+        SimulationState{traffic, occupancy, rng, statistics, ..state}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use network::Network;
+    use city_map::create_city;
+    use occupancy::{Granularity, Occupancy};
+    use routes::Routes;
+    use simulation::{Simulation, SimulationState, SimulationStep, UpdateVehicles, VehicleUpdate, create_rng};
+    use steps::block_occupier::{VehicleFree, VehicleOccupy};
+    use steps::lookahead_driver::LookaheadDriver;
+    use steps::spawner::{RemoveVehicles, SpawnVehicles};
+    use {Traffic, Trip};
+
+    // A 6x6 grid of roads, with sources down the left and two destinations on the right
+    fn run(seed: usize, steps: usize) -> Vec<Traffic> {
+        let rows: Vec<String> = (0..6).map(|y| {
+            let mut cells = vec!["**".to_string(); 6];
+            cells[0] = "** S<0".to_string();
+            if y == 1 || y == 4 {
+                cells[5] = format!("** D>{}", y / 4);
+            }
+            cells.join(",")
+        }).collect();
+        let city = Arc::new(create_city(&rows.join("\n")));
+        let network = Network::new(city.get_num_nodes(), &city.create_edges());
+        let costs = city.destinations.iter().map(|d| network.dijkstra(d.clone())).collect();
+        let routes = Rc::new(RefCell::new(Routes::new(network, city.destinations.clone(), costs)));
+        let updates: Vec<Box<VehicleUpdate>> = vec![
+            Box::new(VehicleFree::new(4)),
+            Box::new(LookaheadDriver::new(3, routes)),
+            Box::new(VehicleOccupy::new(4)),
+        ];
+        let simulation = Simulation{ steps: vec![
+            Box::new(SpawnVehicles::new(Arc::clone(&city), Granularity::Block(4), 2)),
+            Box::new(UpdateVehicles{ updates }),
+            Box::new(RemoveVehicles{}),
+        ] };
+
+        let traffic = Traffic::new(vec![]);
+        let mut state = SimulationState::new(traffic, Occupancy::new(city.get_num_nodes()), create_rng(seed));
+        let mut out = vec![];
+        for _ in 0..steps {
+            state = simulation.step(state);
+            out.push(state.traffic.clone());
+        }
+        out
+    }
+
+    #[test]
+    fn same_seed_same_traffic() {
+        let first = run(42, 100);
+        assert!(first.iter().any(|t| t.vehicles.len() > 3));
+        assert!(first == run(42, 100));
+    }
+
+    #[test]
+    fn different_seed_different_traffic() {
+        assert!(run(1, 100) != run(2, 100));
+    }
+
+    #[test]
+    fn keeps_completed_trips() {
+        let trips: Vec<Trip> = run(42, 100).into_iter().flat_map(|t| t.completed).collect();
+        assert!(!trips.is_empty());
+        let mut ids: Vec<usize> = trips.iter().map(|t| t.vehicle).collect();
+        ids.sort();
+        ids.dedup();
+        assert!(ids.len() == trips.len());
+        assert!(trips.iter().all(|t| t.origin_group == 0 && t.moves > 0 && t.arrived && t.end_step > t.spawn_step));
+    }
+
+    #[test]
+    fn counts_blocked_spawns() {
+        let city = Arc::new(create_city("S>0 >>,D>0"));
+        let spawn = SpawnVehicles::new(Arc::clone(&city), Granularity::Node, 1);
+        let traffic = Traffic::new(vec![]);
+        let state = SimulationState::new(traffic, Occupancy::new(city.get_num_nodes()), create_rng(0));
+        let state = spawn.step(spawn.step(state));
+        assert!(state.statistics.spawned == 1);
+        assert!(state.statistics.blocked_spawns == 1);
+    }
+
+}
@@ -1,5 +1,5 @@
 use std::cell::{Cell, RefCell};
-use occupancy::Occupancy;
+use occupancy::{Holder, Occupancy};
 use simulation::{SimulationState, SimulationStep};
 use std::collections::HashSet;
 
@@ -23,28 +23,39 @@ impl TrafficLights {
         out
     }
 
+    // Each lock is held by the cycle which closed the node: the last cycle before this one which the node is in
+    fn closed_by(&self, cycle: usize, node: usize) -> usize {
+        let cycles = self.nodes.len();
+        (1..cycles + 1)
+            .map(|i| (cycle + cycles - i) % cycles)
+            .find(|c| self.nodes[*c].contains(&node))
+            .unwrap()
+    }
+
     fn unlock(&self, cycle: usize, occupancy: &mut Occupancy) {
         for node in self.nodes[cycle].iter() {
-            occupancy.unlock(*node);
+            let holder = Holder::TrafficLight(self.closed_by(cycle, *node));
+            if let Err(e) = occupancy.unlock_fully_as(*node, holder) {
+                panic!("{}", e);
+            }
         }
     }
 
     fn lock(&self, cycle: usize, occupancy: &mut Occupancy) {
         for node in self.nodes[cycle].iter() {
-            occupancy.lock(*node);
+            occupancy.lock_fully_as(*node, Holder::TrafficLight(cycle));
         }
     }
 
     fn lock_all(&self, occupancy: &mut Occupancy) {
         let mut unique = HashSet::new();
-        for nodes in self.nodes.iter() {
+        for (cycle, nodes) in self.nodes.iter().enumerate() {
             for node in nodes {
-                unique.insert(*node);
+                if unique.insert(*node) {
+                    occupancy.lock_fully_as(*node, Holder::TrafficLight(self.closed_by(cycle, *node)));
+                }
             }
         }
-        for node in unique.iter() {
-            occupancy.lock(*node);
-        }
     }
 
 }
@@ -73,7 +84,7 @@ mod tests {
     use std::cell::RefCell;
     use Traffic;
     use steps::traffic_lights::{TrafficLights, Timer};
-    use occupancy::Occupancy;
+    use occupancy::{Holder, Occupancy};
     use rand::Rng;
     use simulation::{SimulationStep, SimulationState};
 
@@ -105,13 +116,13 @@ mod tests {
 
     #[test]
     fn cycle_once() {
-        let traffic = Traffic{ id: 0, vehicles: vec![] };
+        let traffic = Traffic::new(vec![]);
         let rng: Box<Rng> = Box::new(rand::thread_rng());
         let mut occupancy = Occupancy::new(6);
         let traffic_lights = TrafficLights::new(vec![vec![1, 3], vec![1, 4]],
                                                 RefCell::new(Box::new(MockTimer{})),
                                                 &mut occupancy);
-        let mut state = SimulationState{ traffic, rng, occupancy };
+        let mut state = SimulationState::new(traffic, occupancy, rng);
         state = traffic_lights.step(state);
         let occupancy = state.occupancy;
 
@@ -125,13 +136,13 @@ mod tests {
     
     #[test]
     fn cycle_twice() {
-        let traffic = Traffic{ id: 0, vehicles: vec![] };
+        let traffic = Traffic::new(vec![]);
         let rng: Box<Rng> = Box::new(rand::thread_rng());
         let mut occupancy = Occupancy::new(6);
         let traffic_lights = TrafficLights::new(vec![vec![1, 3], vec![1, 4]],
                                                 RefCell::new(Box::new(MockTimer{})),
                                                 &mut occupancy);
-        let mut state = SimulationState{ traffic, rng, occupancy };
+        let mut state = SimulationState::new(traffic, occupancy, rng);
         state = traffic_lights.step(state);
         state = traffic_lights.step(state);
         let occupancy = state.occupancy;
@@ -144,4 +155,37 @@ mod tests {
         assert!(occupancy.is_unlocked(5));
     }
 
+    #[test]
+    fn locks_held_by_closing_cycle() {
+        let traffic = Traffic::new(vec![]);
+        let rng: Box<Rng> = Box::new(rand::thread_rng());
+        let mut occupancy = Occupancy::with_ownership(6);
+        let traffic_lights = TrafficLights::new(vec![vec![1, 3], vec![1, 4], vec![5]],
+                                                RefCell::new(Box::new(MockTimer{})),
+                                                &mut occupancy);
+        assert!(occupancy.get_holders(4) == [Holder::TrafficLight(1)]);
+        assert!(occupancy.get_holders(5) == [Holder::TrafficLight(2)]);
+        let mut state = SimulationState::new(traffic, occupancy, rng);
+        for _ in 0..4 {
+            state = traffic_lights.step(state);
+        }
+        // Back in the second cycle, having closed the first
+        assert!(state.occupancy.get_holders(3) == [Holder::TrafficLight(0)]);
+        assert!(state.occupancy.get_holders(5) == [Holder::TrafficLight(2)]);
+        assert!(state.occupancy.is_unlocked(1));
+        assert!(state.occupancy.is_unlocked(4));
+    }
+
+    #[test]
+    fn red_light_blocks_whole_capacity() {
+        let mut occupancy = Occupancy::new(6);
+        occupancy.set_capacity(4, 3);
+        let traffic_lights = TrafficLights::new(vec![vec![1, 3], vec![1, 4]],
+                                                RefCell::new(Box::new(MockTimer{})),
+                                                &mut occupancy);
+        assert!(!occupancy.is_unlocked(4));
+        let state = traffic_lights.step(SimulationState::new(Traffic::new(vec![]), occupancy, Box::new(rand::thread_rng())));
+        assert!(state.occupancy.get_remaining(4) == 3);
+    }
+
 }